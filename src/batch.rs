@@ -0,0 +1,79 @@
+//! Batch mode: discover every `typst.toml` under a root and run the full
+//! build pipeline for each, aggregating successes and failures instead of
+//! aborting on the first error.
+
+use crate::config::PackagingFlags;
+use crate::{build_package_at, resolve_toml_path};
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Recursively find and build every package under `root`, printing a
+/// per-package result plus a summary. Returns an error (so the process
+/// exits non-zero) if any discovered package failed to build.
+pub fn build_all(root: &Path, output_dir: &str, flags: PackagingFlags) -> Result<()> {
+    let manifests = discover_manifests(root)?;
+    if manifests.is_empty() {
+        return Err(anyhow!("No typst.toml files found under {}", root.display()));
+    }
+
+    println!("Found {} package(s) under {}", manifests.len(), root.display());
+
+    let mut successes: Vec<(String, String)> = Vec::new();
+    let mut failures: Vec<(std::path::PathBuf, String)> = Vec::new();
+
+    for manifest in &manifests {
+        let toml_path = match resolve_toml_path(manifest) {
+            Ok(path) => path,
+            Err(e) => {
+                failures.push((manifest.clone(), e.to_string()));
+                continue;
+            }
+        };
+
+        println!("\nBuilding: {}", toml_path.display());
+        match build_package_at(&toml_path, output_dir, flags) {
+            Ok((name, version, final_output_dir)) => {
+                println!(
+                    "  OK: '{}' v{} -> {}",
+                    name,
+                    version,
+                    final_output_dir.display()
+                );
+                successes.push((name, version));
+            }
+            Err(e) => {
+                println!("  FAILED: {}: {}", toml_path.display(), e);
+                failures.push((toml_path, e.to_string()));
+            }
+        }
+    }
+
+    println!(
+        "\nBatch build finished: {} succeeded, {} failed",
+        successes.len(),
+        failures.len()
+    );
+
+    if !failures.is_empty() {
+        for (path, err) in &failures {
+            println!("  - {}: {}", path.display(), err);
+        }
+        return Err(anyhow!("{} package(s) failed to build", failures.len()));
+    }
+
+    Ok(())
+}
+
+fn discover_manifests(root: &Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut manifests = Vec::new();
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == "typst.toml")
+    {
+        manifests.push(entry.path().to_path_buf());
+    }
+    manifests.sort();
+    Ok(manifests)
+}
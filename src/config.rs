@@ -1,7 +1,16 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// How `list`/`search` should print their results.
+#[derive(Debug, Default, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
@@ -13,8 +22,30 @@ pub struct Cli {
 pub enum Commands {
     /// Build a Typst package or template
     Build(BuildArgs),
-    /// Install a Typst package from a Git repository
+    /// Install a Typst package, either from a Git repository or as an
+    /// `@namespace/package:version` registry spec
+    #[command(alias = "add")]
     Install(InstallArgs),
+    /// Re-hash packages pinned in typm.lock and refuse on any mismatch
+    Verify(VerifyArgs),
+    /// List installed Typst packages
+    List(ListArgs),
+    /// Search installed Typst packages by name, namespace, and version
+    Search(SearchArgs),
+    /// Remove installed package versions to reclaim disk space
+    #[command(alias = "gc")]
+    Prune(PruneArgs),
+}
+
+/// CLI-level overrides for how a package's files are matched and walked
+/// during packaging. Shared between `build` and `install` so both commands
+/// expose the same knobs over `copy_files`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PackagingFlags {
+    pub exclude_case_insensitive: bool,
+    pub exclude_literal_separator: bool,
+    pub exclude_backslash_escape: bool,
+    pub no_vcs_ignore: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -23,24 +54,153 @@ pub struct BuildArgs {
     pub toml_file: PathBuf,
     #[arg(long, value_name = "OUTPUT_DIR", default_value = "output", value_parser = ["output", "universe"])]
     pub output_dir: String,
+    /// Match exclude patterns case-insensitively. Overrides `[tool.typm]` in typst.toml.
+    #[arg(long)]
+    pub exclude_case_insensitive: bool,
+    /// Don't let `*`/`**` in exclude patterns cross path separators. Overrides `[tool.typm]`.
+    #[arg(long)]
+    pub exclude_literal_separator: bool,
+    /// Treat `\` as an escape character in exclude patterns. Overrides `[tool.typm]`.
+    #[arg(long)]
+    pub exclude_backslash_escape: bool,
+    /// Treat TOML_FILE as a root directory and build every typst.toml found
+    /// beneath it, instead of a single package.
+    #[arg(long)]
+    pub batch: bool,
+    /// Copy the literal directory contents instead of skipping files matched
+    /// by .gitignore/.ignore. Git's tracked file set is not consulted either way.
+    #[arg(long)]
+    pub no_vcs_ignore: bool,
+}
+
+impl BuildArgs {
+    pub fn packaging_flags(&self) -> PackagingFlags {
+        PackagingFlags {
+            exclude_case_insensitive: self.exclude_case_insensitive,
+            exclude_literal_separator: self.exclude_literal_separator,
+            exclude_backslash_escape: self.exclude_backslash_escape,
+            no_vcs_ignore: self.no_vcs_ignore,
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
 pub struct InstallArgs {
-    /// URL of the Git repository or path to a local Git repository.
+    /// A Git source, or an `@namespace/package:version` registry spec.
     /// Examples:
     /// - https://github.com/user/repo
     /// - https://github.com/user/repo.git
     /// - https://github.com/user/repo/tree/main/path/to/package_dir
-    /// The tool will attempt to clone and find a typst.toml in the specified
-    /// repository path (or root if no path is specified in the URL).
+    /// - ./path/to/local/package
+    /// - @preview/cetz:0.2.2
+    /// A Git source is cloned and its typst.toml located (or root if no path
+    /// is specified in the URL); a registry spec is downloaded from the
+    /// matching package registry instead.
     #[arg(value_name = "GIT_SOURCE")]
     pub git_source: String,
-    // Optional: Specify a branch, tag, or commit hash.
-    // If not provided and the URL doesn't specify one (e.g., in a /tree/REF/path pattern),
-    // the repository's default branch will be used.
-    // #[arg(long)]
-    // pub git_ref: Option<String>, // Future enhancement
+    /// Pin the installed revision to this exact commit, overriding any ref
+    /// encoded in GIT_SOURCE. Works even when the commit isn't a branch tip.
+    #[arg(long, conflicts_with_all = ["tag", "branch"])]
+    pub rev: Option<String>,
+    /// Pin the installed revision to this tag, overriding any ref encoded in
+    /// GIT_SOURCE.
+    #[arg(long, conflicts_with_all = ["rev", "branch"])]
+    pub tag: Option<String>,
+    /// Pin the installed revision to this branch, overriding any ref encoded
+    /// in GIT_SOURCE.
+    #[arg(long, conflicts_with_all = ["rev", "tag"])]
+    pub branch: Option<String>,
+    /// Copy the literal directory contents instead of skipping files matched
+    /// by .gitignore/.ignore. Git's tracked file set is not consulted either way.
+    #[arg(long)]
+    pub no_vcs_ignore: bool,
+    /// Verify the resolved source against the existing typst.lock: fails
+    /// unless that lock exists and every file's integrity matches it. The
+    /// source is still copied into the install directory before the check;
+    /// on a mismatch the existing typst.lock is left unchanged.
+    #[arg(long)]
+    pub frozen: bool,
+}
+
+impl InstallArgs {
+    pub fn packaging_flags(&self) -> PackagingFlags {
+        PackagingFlags {
+            no_vcs_ignore: self.no_vcs_ignore,
+            ..PackagingFlags::default()
+        }
+    }
+
+    /// The explicit `--rev`/`--tag`/`--branch` override, if any was given.
+    pub fn git_ref_override(&self) -> Option<&str> {
+        self.rev
+            .as_deref()
+            .or(self.tag.as_deref())
+            .or(self.branch.as_deref())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct VerifyArgs {
+    /// Verify only this `@namespace/package:version`, instead of every
+    /// entry in typm.lock.
+    #[arg(value_name = "SPEC")]
+    pub spec: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListArgs {
+    /// Only list local packages (every namespace in the data directory).
+    #[arg(long)]
+    pub local: bool,
+    /// Only list preview packages (the cache directory).
+    #[arg(long)]
+    pub preview: bool,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct SearchArgs {
+    /// Package name to search for. Supports `*`/`?` glob wildcards;
+    /// otherwise matched as a case-insensitive substring.
+    #[arg(value_name = "QUERY")]
+    pub query: String,
+    /// Only match packages under this namespace (e.g. `preview`, `local`).
+    #[arg(long)]
+    pub namespace: Option<String>,
+    /// Only match versions satisfying this requirement (e.g. `^0.2`, `>=1.0.0`).
+    #[arg(long, value_name = "VERSION_REQ")]
+    pub version: Option<String>,
+    /// For each matching package, show only its highest matching version.
+    #[arg(long)]
+    pub latest: bool,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct PruneArgs {
+    /// Explicit `@namespace/package:version` specs to remove, instead of
+    /// applying --keep-latest/--older-than across every installed package.
+    #[arg(value_name = "SPEC")]
+    pub specs: Vec<String>,
+    /// For each installed package, keep only its N highest semver versions
+    /// and mark the rest for removal.
+    #[arg(long, value_name = "N")]
+    pub keep_latest: Option<usize>,
+    /// Mark versions whose install directory hasn't been modified in this
+    /// long for removal. Accepts a number followed by `s`/`m`/`h`/`d`
+    /// (e.g. `30d`, `12h`).
+    #[arg(long, value_name = "DURATION")]
+    pub older_than: Option<String>,
+    /// Only consider packages under this namespace.
+    #[arg(long)]
+    pub namespace: Option<String>,
+    /// Report what would be removed without deleting anything.
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -50,6 +210,11 @@ pub struct PackageConfig {
     pub exclude: Option<Vec<String>>,
     pub entrypoint: Option<String>,
     pub compiler: Option<String>, // Added for compiler version check
+    pub authors: Option<Vec<String>>,
+    pub description: Option<String>,
+    /// Maps a `"@namespace/name:version-req"` dependency spec to the Git
+    /// source `install` should clone to resolve it.
+    pub dependencies: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,4 +228,25 @@ pub struct TemplateConfig {
 pub struct Config {
     pub package: PackageConfig,
     pub template: Option<TemplateConfig>,
+    pub tool: Option<ToolConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ToolConfig {
+    pub typm: Option<TypmToolConfig>,
+}
+
+/// Per-package glob matching options, set under `[tool.typm]` in typst.toml.
+#[derive(Debug, Default, Deserialize)]
+pub struct TypmToolConfig {
+    #[serde(default)]
+    pub exclude_case_insensitive: bool,
+    #[serde(default)]
+    pub exclude_literal_separator: bool,
+    #[serde(default)]
+    pub exclude_backslash_escape: bool,
+    /// Disable .gitignore/.ignore-aware traversal for this package, copying
+    /// the literal directory contents instead.
+    #[serde(default)]
+    pub disable_vcs_ignore: bool,
 }
@@ -1,5 +1,5 @@
+use crate::ignore_match::{GlobMatchOptions, IgnoreMatcher};
 use anyhow::{anyhow, Context, Result};
-use globset::{Glob, GlobSetBuilder};
 use regex::Regex;
 use std::{
     fs,
@@ -119,55 +119,65 @@ pub fn generate_thumbnail(
     Ok(())
 }
 
+/// Resolve a `#import` target that is relative to `file_dir`, normalizing
+/// `./` and `../` components lexically without touching the filesystem.
+fn resolve_relative_import(file_dir: &Path, target: &str) -> std::path::PathBuf {
+    let mut components: Vec<std::ffi::OsString> = file_dir
+        .components()
+        .map(|c| c.as_os_str().to_os_string())
+        .collect();
+
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            other => components.push(other.into()),
+        }
+    }
+
+    components.iter().collect()
+}
+
+/// Package-level default excludes, applied regardless of whether the package
+/// has its own `.gitignore`/`.ignore`/`.typmignore`, so build artifacts and
+/// editor dumps never ship just because a package was packaged from an
+/// exported tree with no ignore files of its own. Listed first so an
+/// explicit `exclude` pattern (including a `!`-negation) still wins, per
+/// `IgnoreMatcher`'s last-match-wins ordering.
+const DEFAULT_EXCLUDES: &[&str] = &["target/", ".git/", ".DS_Store", ".vscode/", ".idea/"];
+
 pub fn copy_files(
     source_dir: &Path,
     dest_dir: &Path,
     exclude_patterns: &[String],
     package_name: &str,
     package_version: &str,
-    package_entrypoint: &str,
+    glob_options: GlobMatchOptions,
+    respect_vcs_ignore: bool,
 ) -> Result<()> {
     fs::create_dir_all(dest_dir)
         .with_context(|| format!("Failed to create destination directory: {}", dest_dir.display()))?;
 
-    let mut glob_builder = GlobSetBuilder::new();
-    for pattern in exclude_patterns {
-        let glob = Glob::new(pattern)
-            .with_context(|| format!("Invalid glob pattern: '{}'", pattern))?;
-        glob_builder.add(glob);
-    }
-    let glob_set = glob_builder.build()
-        .with_context(|| "Failed to build glob set from exclude patterns")?;
-
-    let directory_patterns: Vec<String> = exclude_patterns
+    let all_exclude_patterns: Vec<String> = DEFAULT_EXCLUDES
         .iter()
-        .filter(|p| !has_glob_metacharacters(p))
-        .filter_map(|p| {
-            let pattern_native = p.replace('/', &std::path::MAIN_SEPARATOR.to_string());
-            let pattern_path = source_dir.join(&pattern_native);
-            let is_dir_pattern = p.ends_with('/') || pattern_path.is_dir();
-            is_dir_pattern.then(|| {
-                pattern_native
-                    .trim_end_matches(std::path::MAIN_SEPARATOR)
-                    .to_string()
-            })
-        })
+        .map(|p| p.to_string())
+        .chain(exclude_patterns.iter().cloned())
         .collect();
+    let ignore_matcher = IgnoreMatcher::new(&all_exclude_patterns, source_dir, glob_options)
+        .with_context(|| "Failed to build exclude matcher")?;
 
-    let entrypoint_name = Path::new(package_entrypoint)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| anyhow!("Invalid entrypoint name: {}", package_entrypoint))?;
-
-    let import_re = Regex::new(&format!(
-        r#"#import\s+"((?:\.\./)+{})((?::\s*[^"]*)?)""#,
-        regex::escape(entrypoint_name)
-    ))?;
+    // Matches any relative (non-`@`, non-root-absolute) `#import` target so
+    // we can rewrite it once its resolved location is known to live inside
+    // the package, regardless of how many `../` hops it takes to get there.
+    let import_re = Regex::new(r#"#import\s+"([^"@][^"]*)"((?:\s*:\s*[^"\n]*)?)"#)?;
     let package_import_str = format!("@preview/{}:{}", package_name, package_version);
 
-    for entry in WalkDir::new(source_dir) {
-        let entry = entry.with_context(|| format!("Error walking directory: {}", source_dir.display()))?;
-        let src_path = entry.path();
+    let entries = collect_package_entries(source_dir, respect_vcs_ignore)?;
+
+    for (src_path, is_dir) in entries {
+        let src_path = src_path.as_path();
         let rel_path = src_path.strip_prefix(source_dir).with_context(|| {
             format!("Failed to strip prefix '{}' from '{}'", source_dir.display(), src_path.display())
         })?;
@@ -176,24 +186,17 @@ pub fn copy_files(
             .ok_or_else(|| anyhow!("Path contains non-UTF8 characters: {:?}", rel_path))?
             .replace(std::path::MAIN_SEPARATOR, "/");
 
-        if glob_set.is_match(&rel_str_unix) {
+        if rel_str_unix.is_empty() {
             continue;
         }
 
-        let rel_str_native = rel_path.to_str().unwrap(); // Already checked for UTF8
-
-        let excluded_by_dir = directory_patterns.iter().any(|pattern| {
-            rel_str_native == pattern
-                || rel_str_native.starts_with(&format!("{}{}", pattern, std::path::MAIN_SEPARATOR))
-        });
-
-        if excluded_by_dir {
+        if ignore_matcher.is_excluded(&rel_str_unix, is_dir) {
             continue;
         }
 
         let dst_path = dest_dir.join(rel_path);
 
-        if entry.file_type().is_dir() {
+        if is_dir {
             fs::create_dir_all(&dst_path)
                 .with_context(|| format!("Failed to create directory: {}", dst_path.display()))?;
         } else {
@@ -216,9 +219,33 @@ pub fn copy_files(
                 let content = fs::read_to_string(src_path)
                     .with_context(|| format!("Failed to read .typ file: {}", src_path.display()))?;
 
+                let file_dir = src_path.parent().unwrap_or(source_dir);
                 let new_content = import_re.replace_all(&content, |caps: &regex::Captures| {
-                    let specifier = caps.get(2).map_or("", |m| m.as_str());
-                    format!("#import \"{}{}\"", package_import_str, specifier)
+                    let whole_match = caps.get(0).unwrap().as_str();
+                    let target = caps.get(1).map_or("", |m| m.as_str());
+                    let selector = caps.get(2).map_or("", |m| m.as_str());
+
+                    if target.starts_with('/') {
+                        // Root-absolute import; not this function's concern.
+                        return whole_match.to_string();
+                    }
+
+                    let resolved = resolve_relative_import(file_dir, target);
+                    match resolved.strip_prefix(source_dir) {
+                        Ok(rel_in_pkg) => {
+                            let rel_unix = rel_in_pkg
+                                .to_str()
+                                .unwrap_or_default()
+                                .replace(std::path::MAIN_SEPARATOR, "/");
+                            if rel_unix.is_empty() {
+                                format!("#import \"{}\"{}", package_import_str, selector)
+                            } else {
+                                format!("#import \"{}/{}\"{}", package_import_str, rel_unix, selector)
+                            }
+                        }
+                        // Import escapes the package tree; leave it untouched.
+                        Err(_) => whole_match.to_string(),
+                    }
                 });
                 fs::write(&dst_path, new_content.as_bytes())
                     .with_context(|| format!("Failed to write modified .typ file to: {}", dst_path.display()))?;
@@ -232,6 +259,137 @@ pub fn copy_files(
     Ok(())
 }
 
-pub fn has_glob_metacharacters(s: &str) -> bool {
-    s.contains(['*', '?', '[']) // ']' is only a metacharacter if '[' is present
+/// Walk `source_dir`, yielding `(path, is_dir)` pairs for everything that
+/// should be considered for packaging. When `respect_vcs_ignore` is set,
+/// this layers `.gitignore`/`.ignore`/global git excludes on top of a plain
+/// walk (like Cargo consulting the repository's tracked file set), so
+/// `target/`, editor dumps, and `.git/` itself never make it into the
+/// published package. Disabling it restores the literal directory contents.
+fn collect_package_entries(source_dir: &Path, respect_vcs_ignore: bool) -> Result<Vec<(std::path::PathBuf, bool)>> {
+    let mut entries = Vec::new();
+
+    if respect_vcs_ignore {
+        let walker = ignore::WalkBuilder::new(source_dir)
+            .hidden(false)
+            .git_ignore(true)
+            .git_exclude(true)
+            .ignore(true)
+            .build();
+
+        for result in walker {
+            let entry = result
+                .with_context(|| format!("Error walking directory: {}", source_dir.display()))?;
+            let path = entry.path();
+            if path == source_dir {
+                continue;
+            }
+            if path.components().any(|c| c.as_os_str() == ".git") {
+                continue;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            entries.push((path.to_path_buf(), is_dir));
+        }
+    } else {
+        for entry in WalkDir::new(source_dir) {
+            let entry = entry
+                .with_context(|| format!("Error walking directory: {}", source_dir.display()))?;
+            let is_dir = entry.file_type().is_dir();
+            entries.push((entry.path().to_path_buf(), is_dir));
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore_match::GlobMatchOptions;
+
+    #[test]
+    fn copy_files_rewrites_relative_imports_with_and_without_a_selector() {
+        let source = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        fs::create_dir_all(source.path().join("lib")).unwrap();
+        fs::write(source.path().join("lib/lib.typ"), "#let x = 1;\n").unwrap();
+        fs::write(
+            source.path().join("main.typ"),
+            "#import \"lib/lib.typ\": x, y\n#import \"lib/lib.typ\"\n",
+        )
+        .unwrap();
+
+        copy_files(
+            source.path(),
+            dest.path(),
+            &[],
+            "mypkg",
+            "1.0.0",
+            GlobMatchOptions::default(),
+            true,
+        )
+        .unwrap();
+
+        let rewritten = fs::read_to_string(dest.path().join("main.typ")).unwrap();
+        assert!(rewritten.contains("#import \"@preview/mypkg:1.0.0/lib/lib.typ\": x, y"));
+        assert!(rewritten.contains("#import \"@preview/mypkg:1.0.0/lib/lib.typ\"\n"));
+    }
+
+    #[test]
+    fn copy_files_leaves_absolute_and_package_imports_untouched() {
+        let source = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        fs::write(
+            source.path().join("main.typ"),
+            "#import \"/root.typ\"\n#import \"@preview/other:0.1.0\": z\n",
+        )
+        .unwrap();
+
+        copy_files(
+            source.path(),
+            dest.path(),
+            &[],
+            "mypkg",
+            "1.0.0",
+            GlobMatchOptions::default(),
+            true,
+        )
+        .unwrap();
+
+        let rewritten = fs::read_to_string(dest.path().join("main.typ")).unwrap();
+        assert!(rewritten.contains("#import \"/root.typ\"\n"));
+        assert!(rewritten.contains("#import \"@preview/other:0.1.0\": z\n"));
+    }
+
+    #[test]
+    fn copy_files_skips_default_excludes_even_without_an_ignore_file() {
+        let source = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        fs::create_dir_all(source.path().join("target/debug")).unwrap();
+        fs::write(source.path().join("target/debug/build.log"), "x").unwrap();
+        fs::write(source.path().join(".DS_Store"), "x").unwrap();
+        fs::create_dir_all(source.path().join(".vscode")).unwrap();
+        fs::write(source.path().join(".vscode/settings.json"), "x").unwrap();
+        fs::write(source.path().join("main.typ"), "#let x = 1;\n").unwrap();
+
+        copy_files(
+            source.path(),
+            dest.path(),
+            &[],
+            "mypkg",
+            "1.0.0",
+            GlobMatchOptions::default(),
+            // No .gitignore/.ignore is present, so this exercises the
+            // default excludes independent of respect_vcs_ignore.
+            false,
+        )
+        .unwrap();
+
+        assert!(!dest.path().join("target").exists());
+        assert!(!dest.path().join(".DS_Store").exists());
+        assert!(!dest.path().join(".vscode").exists());
+        assert!(dest.path().join("main.typ").exists());
+    }
 }
@@ -0,0 +1,38 @@
+//! Parsing for `[dependencies]` entries in a package's `typst.toml`.
+
+use anyhow::{anyhow, Context, Result};
+
+/// One `[dependencies]` entry: the package it's pinned to, plus where
+/// `install` should fetch it from if it isn't already satisfied on disk.
+/// Parsed out of `"@namespace/name:version-req" = "git-source"`.
+pub struct DependencySpec {
+    pub namespace: String,
+    pub name: String,
+    pub version_req: semver::VersionReq,
+    pub git_source: String,
+}
+
+/// Parse a single `[dependencies]` table entry.
+pub fn parse_dependency_spec(key: &str, git_source: &str) -> Result<DependencySpec> {
+    let stripped = key.strip_prefix('@').ok_or_else(|| {
+        anyhow!(
+            "Dependency key '{}' must be of the form \"@namespace/name:version-req\"",
+            key
+        )
+    })?;
+    let (namespace_and_name, version_str) = stripped.split_once(':').ok_or_else(|| {
+        anyhow!("Dependency key '{}' is missing a ':version-req' requirement", key)
+    })?;
+    let (namespace, name) = namespace_and_name.split_once('/').ok_or_else(|| {
+        anyhow!("Dependency key '{}' is missing a '/name' component", key)
+    })?;
+    let version_req = semver::VersionReq::parse(version_str)
+        .with_context(|| format!("Invalid version requirement in dependency key '{}'", key))?;
+
+    Ok(DependencySpec {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        version_req,
+        git_source: git_source.to_string(),
+    })
+}
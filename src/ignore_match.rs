@@ -0,0 +1,342 @@
+//! Ordered, gitignore-style exclude matching for `copy_files`.
+//!
+//! Patterns are evaluated top to bottom against each unix-style relative
+//! path; the last pattern that matches wins, which is what lets a later
+//! `!keep-me` re-include something an earlier broad pattern excluded.
+//!
+//! Each entry may carry an explicit syntax prefix (`glob:`, `re:`, `path:`,
+//! `rootfilesin:`); glob is assumed when no prefix is present.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// Glob match options for the `glob:` (default) pattern syntax, analogous to
+/// `globset::GlobBuilder`'s knobs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobMatchOptions {
+    pub case_insensitive: bool,
+    /// When true, neither `*` nor `**` ever matches a path separator, so
+    /// e.g. `assets/**` only matches direct children of `assets/`.
+    pub literal_separator: bool,
+    /// When true, `\` escapes the following character instead of being
+    /// matched literally.
+    pub backslash_escape: bool,
+}
+
+enum MatchKind {
+    /// `glob:` (default) — gitignore-flavored glob, anchored or not,
+    /// optionally directory-only.
+    Glob {
+        regex: Regex,
+        anchored: bool,
+        dir_only: bool,
+    },
+    /// `re:` — a raw regex matched against the full relative path.
+    Regex(Regex),
+    /// `path:` — an exact subtree: the path itself and everything beneath it.
+    Path(String),
+    /// `rootfilesin:` — direct file children of a directory, not nested ones.
+    RootFilesIn(String),
+}
+
+struct CompiledPattern {
+    kind: MatchKind,
+    negate: bool,
+}
+
+/// An ordered set of patterns with last-match-wins semantics.
+pub struct IgnoreMatcher {
+    patterns: Vec<CompiledPattern>,
+}
+
+impl IgnoreMatcher {
+    /// Build a matcher from exclude patterns, optionally prefixed by the
+    /// contents of a `.typmignore` file (each non-blank, non-comment line
+    /// becomes an earlier pattern, so patterns passed explicitly still win
+    /// last unless they are earlier in this list).
+    pub fn new(
+        exclude_patterns: &[String],
+        package_dir: &Path,
+        glob_options: GlobMatchOptions,
+    ) -> Result<Self> {
+        let mut raw_patterns: Vec<String> = Vec::new();
+
+        let typmignore_path = package_dir.join(".typmignore");
+        if typmignore_path.is_file() {
+            let content = fs::read_to_string(&typmignore_path).with_context(|| {
+                format!("Failed to read .typmignore: {}", typmignore_path.display())
+            })?;
+            for line in content.lines() {
+                let trimmed = line.trim();
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                raw_patterns.push(trimmed.to_string());
+            }
+        }
+
+        raw_patterns.extend(exclude_patterns.iter().cloned());
+
+        let patterns = raw_patterns
+            .iter()
+            .map(|p| compile_pattern(p, glob_options))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Decide whether `rel_str_unix` should be excluded, applying every
+    /// pattern in order and keeping only the last decision that applies.
+    pub fn is_excluded(&self, rel_str_unix: &str, is_dir: bool) -> bool {
+        let components: Vec<&str> = rel_str_unix.split('/').collect();
+        let mut excluded = false;
+
+        for pattern in &self.patterns {
+            if pattern_matches(pattern, rel_str_unix, &components, is_dir) {
+                excluded = !pattern.negate;
+            }
+        }
+
+        excluded
+    }
+}
+
+fn pattern_matches(
+    pattern: &CompiledPattern,
+    rel_str_unix: &str,
+    components: &[&str],
+    is_dir: bool,
+) -> bool {
+    match &pattern.kind {
+        MatchKind::Glob {
+            regex,
+            anchored,
+            dir_only,
+        } => glob_matches(regex, *anchored, *dir_only, components, is_dir),
+        MatchKind::Regex(regex) => regex.is_match(rel_str_unix),
+        MatchKind::Path(root) => {
+            rel_str_unix == root || rel_str_unix.starts_with(&format!("{}/", root))
+        }
+        MatchKind::RootFilesIn(dir) => {
+            !is_dir
+                && components.len() > 1
+                && components[..components.len() - 1].join("/") == *dir
+        }
+    }
+}
+
+fn glob_matches(
+    regex: &Regex,
+    anchored: bool,
+    dir_only: bool,
+    components: &[&str],
+    is_dir: bool,
+) -> bool {
+    if anchored {
+        // Check the full path and every ancestor directory, since excluding
+        // a directory implicitly excludes everything beneath it.
+        for i in 1..=components.len() {
+            let prefix_is_dir = i < components.len() || is_dir;
+            if dir_only && !prefix_is_dir {
+                continue;
+            }
+            let candidate = components[..i].join("/");
+            if regex.is_match(&candidate) {
+                return true;
+            }
+        }
+        false
+    } else {
+        // Unanchored single-segment patterns match any path component at
+        // any depth (directories or files, subject to dir_only).
+        for (i, component) in components.iter().enumerate() {
+            let component_is_dir = i + 1 < components.len() || is_dir;
+            if dir_only && !component_is_dir {
+                continue;
+            }
+            if regex.is_match(component) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn compile_pattern(raw: &str, glob_options: GlobMatchOptions) -> Result<CompiledPattern> {
+    let mut pattern = raw;
+
+    let negate = pattern.starts_with('!');
+    if negate {
+        pattern = &pattern[1..];
+    }
+
+    let kind = if let Some(rest) = pattern.strip_prefix("re:") {
+        let regex =
+            Regex::new(rest).with_context(|| format!("Invalid regex exclude pattern: '{}'", raw))?;
+        MatchKind::Regex(regex)
+    } else if let Some(rest) = pattern.strip_prefix("path:") {
+        MatchKind::Path(rest.trim_end_matches('/').to_string())
+    } else if let Some(rest) = pattern.strip_prefix("rootfilesin:") {
+        MatchKind::RootFilesIn(rest.trim_end_matches('/').to_string())
+    } else {
+        let glob_pattern = pattern.strip_prefix("glob:").unwrap_or(pattern);
+        compile_glob(glob_pattern, raw, glob_options)?
+    };
+
+    Ok(CompiledPattern { kind, negate })
+}
+
+fn compile_glob(pattern: &str, raw: &str, glob_options: GlobMatchOptions) -> Result<MatchKind> {
+    let leading_anchor = pattern.starts_with('/');
+    let pattern = if leading_anchor { &pattern[1..] } else { pattern };
+
+    let dir_only = pattern.ends_with('/') && pattern.len() > 1;
+    let pattern = if dir_only {
+        &pattern[..pattern.len() - 1]
+    } else {
+        pattern
+    };
+
+    let anchored = leading_anchor || pattern.contains('/');
+
+    let body = glob_to_regex(pattern, glob_options);
+    let anchored_body = format!("^{}$", body);
+    let regex = if glob_options.case_insensitive {
+        Regex::new(&format!("(?i){}", anchored_body))
+    } else {
+        Regex::new(&anchored_body)
+    }
+    .with_context(|| format!("Invalid exclude pattern: '{}'", raw))?;
+
+    Ok(MatchKind::Glob {
+        regex,
+        anchored,
+        dir_only,
+    })
+}
+
+/// Translate a gitignore-flavored glob into a regex body (no anchors).
+/// By default `**` matches across path separators while a bare `*` stops at
+/// one; `literal_separator` disables that distinction so neither crosses a
+/// separator, and `backslash_escape` lets `\` escape the following character.
+/// A leading `**/` is additionally treated as an optional path prefix (like
+/// gitignore), so `**/foo` matches `foo` at the package root as well as at
+/// any depth, instead of requiring at least one directory hop.
+fn glob_to_regex(glob: &str, options: GlobMatchOptions) -> String {
+    let mut out = String::new();
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if options.backslash_escape && i + 1 < chars.len() => {
+                out.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+            }
+            '*' => {
+                if !options.literal_separator && chars.get(i + 1) == Some(&'*') && chars.get(i + 2) == Some(&'/') {
+                    out.push_str("(?:.*/)?");
+                    i += 3;
+                } else if !options.literal_separator && chars.get(i + 1) == Some(&'*') {
+                    out.push_str(".*");
+                    i += 2;
+                } else {
+                    out.push_str("[^/]*");
+                    i += 1;
+                }
+            }
+            '?' => {
+                out.push_str("[^/]");
+                i += 1;
+            }
+            c => {
+                out.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_to_regex_translates_star_patterns() {
+        let opts = GlobMatchOptions::default();
+        assert_eq!(glob_to_regex("*.typ", opts), "[^/]*\\.typ");
+        assert_eq!(glob_to_regex("assets/**", opts), "assets/.*");
+        assert_eq!(glob_to_regex("a?c", opts), "a[^/]c");
+    }
+
+    #[test]
+    fn glob_to_regex_treats_leading_doublestar_slash_as_optional_prefix() {
+        let opts = GlobMatchOptions::default();
+        assert_eq!(glob_to_regex("**/foo", opts), "(?:.*/)?foo");
+    }
+
+    #[test]
+    fn is_excluded_matches_leading_doublestar_at_every_depth_including_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let matcher =
+            IgnoreMatcher::new(&["**/foo.typ".to_string()], dir.path(), GlobMatchOptions::default()).unwrap();
+
+        assert!(matcher.is_excluded("foo.typ", false));
+        assert!(matcher.is_excluded("nested/foo.typ", false));
+        assert!(matcher.is_excluded("nested/deep/foo.typ", false));
+        assert!(!matcher.is_excluded("other.typ", false));
+    }
+
+    #[test]
+    fn glob_to_regex_respects_literal_separator() {
+        let opts = GlobMatchOptions {
+            literal_separator: true,
+            ..GlobMatchOptions::default()
+        };
+        // With literal_separator set, `**` no longer gets special treatment
+        // and behaves just like a single `*`: it cannot cross a separator.
+        assert_eq!(glob_to_regex("assets/**", opts), "assets/[^/]*[^/]*");
+    }
+
+    #[test]
+    fn glob_to_regex_respects_backslash_escape() {
+        let opts = GlobMatchOptions {
+            backslash_escape: true,
+            ..GlobMatchOptions::default()
+        };
+        assert_eq!(glob_to_regex(r"a\*b", opts), "a\\*b");
+    }
+
+    #[test]
+    fn is_excluded_matches_unanchored_glob_at_any_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let matcher = IgnoreMatcher::new(&["*.tmp".to_string()], dir.path(), GlobMatchOptions::default()).unwrap();
+
+        assert!(matcher.is_excluded("scratch.tmp", false));
+        assert!(matcher.is_excluded("nested/deep/scratch.tmp", false));
+        assert!(!matcher.is_excluded("keep.typ", false));
+    }
+
+    #[test]
+    fn is_excluded_applies_last_matching_pattern_including_negation() {
+        let dir = tempfile::tempdir().unwrap();
+        let patterns = vec!["*.log".to_string(), "!keep.log".to_string()];
+        let matcher = IgnoreMatcher::new(&patterns, dir.path(), GlobMatchOptions::default()).unwrap();
+
+        assert!(matcher.is_excluded("debug.log", false));
+        assert!(!matcher.is_excluded("keep.log", false));
+    }
+
+    #[test]
+    fn is_excluded_treats_anchored_pattern_as_directory_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let matcher =
+            IgnoreMatcher::new(&["/build/".to_string()], dir.path(), GlobMatchOptions::default()).unwrap();
+
+        assert!(matcher.is_excluded("build", true));
+        assert!(matcher.is_excluded("build/output.typ", false));
+        assert!(!matcher.is_excluded("other/build", true));
+    }
+}
@@ -0,0 +1,127 @@
+//! `typst.lock` generation and verification: per-file SRI integrity hashes
+//! for an installed package, modeled on npm's lockfile integrity handling.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+const LOCK_FILE_NAME: &str = "typst.lock";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedFile {
+    pub path: String,
+    pub integrity: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypstLock {
+    pub package: String,
+    pub version: String,
+    pub integrity: String,
+    pub files: Vec<LockedFile>,
+}
+
+/// Compute a `sha256-<base64>` Subresource-Integrity-style digest for `bytes`.
+fn sri_digest(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("sha256-{}", BASE64.encode(hasher.finalize()))
+}
+
+/// Walk `install_dir` and build a `TypstLock` describing every installed
+/// file (except any file named `typst.lock`, at any depth), with digests sorted by relative path
+/// so the aggregate integrity is deterministic regardless of walk order.
+pub fn compute_lock(install_dir: &Path, package: &str, version: &str) -> Result<TypstLock> {
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(install_dir) {
+        let entry = entry
+            .with_context(|| format!("Error walking install directory: {}", install_dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if entry.file_name() == LOCK_FILE_NAME {
+            continue;
+        }
+
+        let rel_path = entry
+            .path()
+            .strip_prefix(install_dir)
+            .with_context(|| format!("Failed to strip prefix from: {}", entry.path().display()))?
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("Non-UTF8 path: {:?}", entry.path()))?
+            .replace(std::path::MAIN_SEPARATOR, "/");
+
+        let bytes = fs::read(entry.path())
+            .with_context(|| format!("Failed to read file for hashing: {}", entry.path().display()))?;
+
+        files.push(LockedFile {
+            path: rel_path,
+            integrity: sri_digest(&bytes),
+        });
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let aggregate_input: String = files
+        .iter()
+        .map(|f| format!("{}:{}\n", f.path, f.integrity))
+        .collect();
+    let integrity = sri_digest(aggregate_input.as_bytes());
+
+    Ok(TypstLock {
+        package: package.to_string(),
+        version: version.to_string(),
+        integrity,
+        files,
+    })
+}
+
+/// Write `lock` as `typst.lock` inside `install_dir`.
+pub fn write_lock(install_dir: &Path, lock: &TypstLock) -> Result<()> {
+    let lock_path = install_dir.join(LOCK_FILE_NAME);
+    let content = toml::to_string_pretty(lock).context("Failed to serialize typst.lock")?;
+    fs::write(&lock_path, content)
+        .with_context(|| format!("Failed to write lockfile: {}", lock_path.display()))
+}
+
+/// Read an existing `typst.lock` from `install_dir`, if present.
+pub fn read_lock(install_dir: &Path) -> Result<Option<TypstLock>> {
+    let lock_path = install_dir.join(LOCK_FILE_NAME);
+    if !lock_path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&lock_path)
+        .with_context(|| format!("Failed to read lockfile: {}", lock_path.display()))?;
+    let lock = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse lockfile: {}", lock_path.display()))?;
+    Ok(Some(lock))
+}
+
+/// Return the relative paths whose digest differs between `old` and `new`,
+/// including files added or removed entirely. Empty means `old` and `new`
+/// describe the same file contents (their aggregate integrity must then also
+/// match, barring a hash collision).
+pub fn diff_files<'a>(old: &'a TypstLock, new: &'a TypstLock) -> Vec<&'a str> {
+    let mut offending = Vec::new();
+
+    for old_file in &old.files {
+        match new.files.iter().find(|f| f.path == old_file.path) {
+            Some(new_file) if new_file.integrity == old_file.integrity => {}
+            _ => offending.push(old_file.path.as_str()),
+        }
+    }
+    for new_file in &new.files {
+        if !old.files.iter().any(|f| f.path == new_file.path) {
+            offending.push(new_file.path.as_str());
+        }
+    }
+
+    offending.sort_unstable();
+    offending.dedup();
+    offending
+}
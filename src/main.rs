@@ -1,10 +1,22 @@
+mod batch;
 mod config;
 mod core;
+mod deps;
+mod ignore_match;
+mod lockfile;
+mod package_error;
+mod project_lock;
+mod registry;
 
 use anyhow::{Context, Result, anyhow};
 use clap::Parser;
-use config::{BuildArgs, Cli, Commands, Config, InstallArgs, ListArgs};
+use config::{
+    BuildArgs, Cli, Commands, Config, InstallArgs, ListArgs, PackagingFlags, PruneArgs, SearchArgs,
+    TypmToolConfig, VerifyArgs,
+};
 use core::{compile_template, copy_files, generate_thumbnail, validate_package_name};
+use ignore_match::GlobMatchOptions;
+use package_error::PackageError;
 use serde::Deserialize;
 use std::{
     fs,
@@ -13,12 +25,52 @@ use std::{
     process::Command,
 };
 
-struct GitSourceDescriptor {
-    repo_url_for_clone: String,
-    git_ref: Option<String>,
-    path_in_repo: PathBuf,
-    provider_host: String,
-    user_or_org: String,
+/// Combine a package's `[tool.typm]` settings with CLI overrides, CLI flags
+/// winning whenever they're set.
+fn resolve_glob_options(tool_config: Option<&TypmToolConfig>, flags: &PackagingFlags) -> GlobMatchOptions {
+    let from_toml = tool_config.map(|t| GlobMatchOptions {
+        case_insensitive: t.exclude_case_insensitive,
+        literal_separator: t.exclude_literal_separator,
+        backslash_escape: t.exclude_backslash_escape,
+    });
+
+    GlobMatchOptions {
+        case_insensitive: flags.exclude_case_insensitive || from_toml.is_some_and(|o| o.case_insensitive),
+        literal_separator: flags.exclude_literal_separator || from_toml.is_some_and(|o| o.literal_separator),
+        backslash_escape: flags.exclude_backslash_escape || from_toml.is_some_and(|o| o.backslash_escape),
+    }
+}
+
+/// Combine a package's `[tool.typm]` setting with the CLI override to decide
+/// whether `copy_files` should consult `.gitignore`/`.ignore`.
+fn resolve_respect_vcs_ignore(tool_config: Option<&TypmToolConfig>, flags: &PackagingFlags) -> bool {
+    let disabled_by_toml = tool_config.is_some_and(|t| t.disable_vcs_ignore);
+    !(flags.no_vcs_ignore || disabled_by_toml)
+}
+
+/// Where a package's source comes from, as resolved from the `install`
+/// argument. `Remote` is cloned from a provider over the network; the local
+/// variants are used directly (or cloned locally) so `install` works offline
+/// against a package that lives beside the caller.
+enum GitSource {
+    Remote {
+        repo_url_for_clone: String,
+        git_ref: Option<String>,
+        path_in_repo: PathBuf,
+        provider_host: String,
+        user_or_org: String,
+    },
+    /// A plain directory on disk (not a Git repository): used as-is, no
+    /// cloning involved.
+    LocalDirectory { path: PathBuf },
+    /// A local Git working tree or bare repository: cloned into a temp dir
+    /// like a remote source, but via a filesystem path instead of a network
+    /// fetch.
+    LocalGitRepo {
+        repo_path: PathBuf,
+        git_ref: Option<String>,
+        path_in_repo: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
@@ -28,28 +80,62 @@ fn main() -> Result<()> {
         Commands::Build(args) => handle_build_command(args),
         Commands::Install(args) => handle_install_command(args),
         Commands::List(args) => handle_list_command(args),
+        Commands::Verify(args) => handle_verify_command(args),
+        Commands::Search(args) => handle_search_command(args),
+        Commands::Prune(args) => handle_prune_command(args),
     }
 }
 
 fn handle_build_command(args: BuildArgs) -> Result<()> {
-    let toml_path = if args.toml_file.is_file() {
-        args.toml_file.clone()
-    } else if args.toml_file.is_dir() {
-        let path = args.toml_file.join("typst.toml");
+    let flags = args.packaging_flags();
+
+    if args.batch {
+        return batch::build_all(&args.toml_file, &args.output_dir, flags);
+    }
+
+    let toml_path = resolve_toml_path(&args.toml_file)?;
+    let (name, version, final_output_dir) = build_package_at(&toml_path, &args.output_dir, flags)?;
+
+    println!(
+        "Package '{}' v{} built successfully to {}",
+        name,
+        version,
+        final_output_dir.display()
+    );
+
+    Ok(())
+}
+
+/// Resolve a `build` target to a concrete `typst.toml` path, accepting
+/// either the manifest itself or its containing directory.
+fn resolve_toml_path(toml_file: &Path) -> Result<PathBuf> {
+    if toml_file.is_file() {
+        Ok(toml_file.to_path_buf())
+    } else if toml_file.is_dir() {
+        let path = toml_file.join("typst.toml");
         if !path.exists() {
             return Err(anyhow!(
                 "No typst.toml found in directory: {}",
-                args.toml_file.display()
+                toml_file.display()
             ));
         }
-        path
+        Ok(path)
     } else {
-        return Err(anyhow!(
+        Err(anyhow!(
             "Path is neither a file nor a directory: {}",
-            args.toml_file.to_string_lossy()
-        ));
-    };
+            toml_file.to_string_lossy()
+        ))
+    }
+}
 
+/// Run the validate -> compile -> thumbnail -> copy pipeline for a single
+/// package manifest. Returns the package name, version, and the directory it
+/// was built into, so callers (single-package or batch) can report on it.
+fn build_package_at(
+    toml_path: &Path,
+    output_dir: &str,
+    flags: PackagingFlags,
+) -> Result<(String, String, PathBuf)> {
     let toml_dir = toml_path.parent().ok_or_else(|| {
         anyhow!(
             "Could not determine parent directory of TOML file: {}",
@@ -64,6 +150,16 @@ fn handle_build_command(args: BuildArgs) -> Result<()> {
 
     validate_package_name(&config.package.name, toml_dir)?;
 
+    let entrypoint = config.package.entrypoint.as_deref().unwrap_or("main.typ");
+    let entrypoint_path = toml_dir.join(entrypoint);
+    if !entrypoint_path.is_file() {
+        return Err(anyhow!(
+            "Package entrypoint '{}' not found at: {}",
+            entrypoint,
+            entrypoint_path.display()
+        ));
+    }
+
     if let Some(template_config) = &config.template {
         if let (Some(template_path), Some(template_entrypoint)) =
             (&template_config.path, &template_config.entrypoint)
@@ -103,11 +199,15 @@ fn handle_build_command(args: BuildArgs) -> Result<()> {
         }
     }
 
-    let output_base_dir = Path::new(&args.output_dir);
+    let output_base_dir = Path::new(output_dir);
     let final_output_dir = output_base_dir
         .join(&config.package.name)
         .join(&config.package.version);
 
+    let tool_config = config.tool.as_ref().and_then(|t| t.typm.as_ref());
+    let glob_options = resolve_glob_options(tool_config, &flags);
+    let respect_vcs_ignore = resolve_respect_vcs_ignore(tool_config, &flags);
+
     println!("Copying files to: {}", final_output_dir.display());
     copy_files(
         toml_dir,
@@ -115,20 +215,56 @@ fn handle_build_command(args: BuildArgs) -> Result<()> {
         &config.package.exclude.clone().unwrap_or_default(),
         &format!("preview/{}", config.package.name),
         &config.package.version,
-        config.package.entrypoint.as_deref().unwrap_or("main.typ"),
+        glob_options,
+        respect_vcs_ignore,
     )?;
 
-    println!(
-        "Package '{}' v{} built successfully to {}",
-        config.package.name,
-        config.package.version,
-        final_output_dir.display()
-    );
+    Ok((config.package.name, config.package.version, final_output_dir))
+}
 
-    Ok(())
+/// Check whether `path` looks like a Git working tree or bare repository
+/// (has a `.git` directory, or is itself a bare repo's top level).
+fn is_git_repo_path(path: &Path) -> bool {
+    path.join(".git").exists() || (path.join("HEAD").is_file() && path.join("objects").is_dir())
 }
 
-fn parse_git_source(git_source_url: &str) -> Result<GitSourceDescriptor> {
+/// Recognize `./path`, `../path`, `/abs/path`, and `file:///abs/path` as
+/// local sources, distinguishing a plain directory from a local Git
+/// working tree/bare repo. Returns `None` for anything else (aliases and
+/// remote URLs), so the caller can fall through to that parsing.
+fn try_parse_local_source(git_source_url: &str) -> Result<Option<GitSource>> {
+    let (is_file_uri, path_str) = match git_source_url.strip_prefix("file://") {
+        Some(rest) => (true, rest),
+        None => (false, git_source_url),
+    };
+
+    let looks_local =
+        is_file_uri || path_str.starts_with("./") || path_str.starts_with("../") || path_str.starts_with('/');
+    if !looks_local {
+        return Ok(None);
+    }
+
+    let path = PathBuf::from(path_str);
+    if !path.exists() {
+        return Err(anyhow!("Local source path does not exist: {}", path.display()));
+    }
+
+    if is_git_repo_path(&path) {
+        Ok(Some(GitSource::LocalGitRepo {
+            repo_path: path,
+            git_ref: None,
+            path_in_repo: PathBuf::new(),
+        }))
+    } else {
+        Ok(Some(GitSource::LocalDirectory { path }))
+    }
+}
+
+fn parse_git_source(git_source_url: &str) -> Result<GitSource> {
+    if let Some(local_source) = try_parse_local_source(git_source_url)? {
+        return Ok(local_source);
+    }
+
     // Try parsing as an alias first: provider_alias/user_or_org/repo_name[/path/in/repo]
     let alias_parts: Vec<&str> = git_source_url.splitn(3, '/').collect();
 
@@ -198,7 +334,7 @@ fn parse_git_source(git_source_url: &str) -> Result<GitSourceDescriptor> {
                     _ => unreachable!("Invalid resolved_host after checks"),
                 };
 
-                return Ok(GitSourceDescriptor {
+                return Ok(GitSource::Remote {
                     repo_url_for_clone,
                     git_ref: None,
                     path_in_repo: PathBuf::from(path_in_repo_str),
@@ -253,7 +389,7 @@ fn parse_git_source(git_source_url: &str) -> Result<GitSourceDescriptor> {
 
             let path_in_repo = PathBuf::from(path_in_repo_parts.join("/"));
 
-            return Ok(GitSourceDescriptor {
+            return Ok(GitSource::Remote {
                 repo_url_for_clone,
                 git_ref,
                 path_in_repo,
@@ -290,7 +426,7 @@ fn parse_git_source(git_source_url: &str) -> Result<GitSourceDescriptor> {
 
             let path_in_repo = PathBuf::from(path_in_repo_parts.join("/"));
 
-            return Ok(GitSourceDescriptor {
+            return Ok(GitSource::Remote {
                 repo_url_for_clone,
                 git_ref,
                 path_in_repo,
@@ -339,46 +475,199 @@ fn get_current_typst_version() -> Result<semver::Version> {
         .with_context(|| format!("Failed to parse typst version: {}", version_part))
 }
 
-fn handle_install_command(args: InstallArgs) -> Result<()> {
-    println!("Attempting to install from: {}", args.git_source);
+/// A package's `typst.toml`, fetched but not yet installed; the fields
+/// `install` needs from a cloned source, whether it's the root package or a
+/// resolved dependency.
+#[derive(Deserialize)]
+struct PackageOnlyConfig {
+    package: config::PackageConfig,
+    tool: Option<config::ToolConfig>,
+}
 
-    let source_desc = parse_git_source(&args.git_source)?;
+/// A resolved package source with its `typst.toml` located and parsed.
+/// `_temp_dir` is kept alive only to stop a clone from being deleted before
+/// `package_source_path` is consumed; it's `None` for a `LocalDirectory`
+/// source, which is used in place rather than cloned.
+struct ResolvedSource {
+    _temp_dir: Option<tempfile::TempDir>,
+    package_source_path: PathBuf,
+    source_desc: GitSource,
+    pkg_config: PackageOnlyConfig,
+}
 
-    let temp_dir = tempfile::Builder::new()
-        .prefix("typst-build-git-")
-        .tempdir()?;
-    let clone_target_dir = temp_dir.path();
+/// A package that `install` (or a dependency it pulled in) actually copied
+/// into the Typst data directory.
+struct InstalledPackage {
+    namespace: String,
+    name: String,
+    version: String,
+    install_dir: PathBuf,
+}
 
-    println!(
-        "Cloning {} into {}...",
-        source_desc.repo_url_for_clone,
-        clone_target_dir.display()
-    );
+/// A ref that looks like a commit SHA (7-40 hex digits) rather than a branch
+/// or tag name: `git clone --branch` can't shallow-clone these directly, so
+/// they need the fetch-then-checkout fallback below.
+fn looks_like_commit_sha(git_ref: &str) -> bool {
+    let git_ref = git_ref.trim();
+    (7..=40).contains(&git_ref.len()) && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Attempt a shallow `--depth 1 --branch <git_ref>` clone. Fails for commit
+/// SHAs on most Git servers, since shallow branch clones only resolve ref
+/// names, not arbitrary commits.
+fn try_shallow_branch_clone(repo_for_clone: &str, git_ref: Option<&str>, dest: &Path) -> Result<()> {
     let mut git_clone_cmd = Command::new("git");
     git_clone_cmd.arg("clone").arg("--depth").arg("1");
-    if let Some(ref git_ref) = source_desc.git_ref {
+    if let Some(git_ref) = git_ref {
         git_clone_cmd.arg("--branch").arg(git_ref);
     }
-    git_clone_cmd
-        .arg(&source_desc.repo_url_for_clone)
-        .arg(clone_target_dir);
+    git_clone_cmd.arg(repo_for_clone).arg(dest);
 
-    let clone_status = git_clone_cmd.status().with_context(|| {
-        format!(
-            "Failed to execute git clone for {}",
-            source_desc.repo_url_for_clone
-        )
-    })?;
+    let clone_status = git_clone_cmd
+        .status()
+        .with_context(|| format!("Failed to execute git clone for {}", repo_for_clone))?;
     if !clone_status.success() {
         return Err(anyhow!(
-            "git clone failed for {}",
-            source_desc.repo_url_for_clone
+            "git clone --branch {:?} failed for {}",
+            git_ref,
+            repo_for_clone
         ));
     }
+    Ok(())
+}
+
+/// Resolve a ref a shallow `--branch` clone can't target (a commit SHA, or a
+/// branch/tag name shallow clone rejected) by cloning without checking out a
+/// working tree, fetching just that revision, then checking it out directly.
+fn fetch_exact_ref(repo_for_clone: &str, git_ref: &str, dest: &Path) -> Result<()> {
+    let clone_status = Command::new("git")
+        .args(["clone", "--no-checkout"])
+        .arg(repo_for_clone)
+        .arg(dest)
+        .status()
+        .with_context(|| format!("Failed to execute git clone --no-checkout for {}", repo_for_clone))?;
+    if !clone_status.success() {
+        return Err(anyhow!("git clone --no-checkout failed for {}", repo_for_clone));
+    }
+
+    let fetch_status = Command::new("git")
+        .arg("-C")
+        .arg(dest)
+        .args(["fetch", "--depth", "1", "origin", git_ref])
+        .status()
+        .with_context(|| format!("Failed to fetch '{}' from {}", git_ref, repo_for_clone))?;
+    if !fetch_status.success() {
+        return Err(anyhow!(
+            "git fetch --depth 1 origin {} failed for {}",
+            git_ref,
+            repo_for_clone
+        ));
+    }
+
+    let checkout_status = Command::new("git")
+        .arg("-C")
+        .arg(dest)
+        .args(["checkout", git_ref])
+        .status()
+        .with_context(|| format!("Failed to check out '{}' in {}", git_ref, dest.display()))?;
+    if !checkout_status.success() {
+        return Err(anyhow!("git checkout {} failed in {}", git_ref, dest.display()));
+    }
+
+    Ok(())
+}
+
+/// Clone `repo_for_clone` (a remote URL or a local repo path, both accepted
+/// by `git clone`) at `git_ref`, returning the temp dir it was cloned into.
+/// Commit SHAs skip straight to the fetch-then-checkout fallback, since a
+/// shallow `--branch` clone can't target them; branch/tag names try the
+/// shallow clone first and only fall back if that fails (e.g. the host
+/// rejects shallow-cloning that particular ref).
+fn clone_into_temp_dir(repo_for_clone: &str, git_ref: Option<&str>) -> Result<tempfile::TempDir> {
+    let temp_dir = tempfile::Builder::new().prefix("typst-build-git-").tempdir()?;
+    println!("Cloning {} into {}...", repo_for_clone, temp_dir.path().display());
+
+    let ref_is_sha = git_ref.is_some_and(looks_like_commit_sha);
+
+    if !ref_is_sha {
+        match try_shallow_branch_clone(repo_for_clone, git_ref, temp_dir.path()) {
+            Ok(()) => {
+                println!("Clone successful.");
+                return Ok(temp_dir);
+            }
+            Err(e) => {
+                let Some(git_ref) = git_ref else {
+                    return Err(e);
+                };
+                println!(
+                    "Shallow clone at ref '{}' failed ({}), falling back to fetching that exact revision...",
+                    git_ref, e
+                );
+            }
+        }
+    }
+
+    let git_ref = git_ref.ok_or_else(|| {
+        anyhow!("Cannot determine which revision to fetch for {}", repo_for_clone)
+    })?;
+    fetch_exact_ref(repo_for_clone, git_ref, temp_dir.path())?;
     println!("Clone successful.");
 
-    // Initial package_source_path based on URL or alias path component
-    let mut package_source_path = clone_target_dir.join(&source_desc.path_in_repo);
+    Ok(temp_dir)
+}
+
+/// Resolve `git_source_url` (remote, local directory, or local Git repo),
+/// locate its `typst.toml` (searching recursively and prompting on
+/// ambiguity), and parse it.
+fn clone_and_locate_package(git_source_url: &str, git_ref_override: Option<&str>) -> Result<ResolvedSource> {
+    let mut source_desc = parse_git_source(git_source_url)?;
+
+    if let Some(git_ref_override) = git_ref_override {
+        match &mut source_desc {
+            GitSource::Remote { git_ref, .. } | GitSource::LocalGitRepo { git_ref, .. } => {
+                *git_ref = Some(git_ref_override.to_string());
+            }
+            GitSource::LocalDirectory { .. } => {
+                return Err(anyhow!(
+                    "--rev/--tag/--branch has no effect on a local directory source (no Git history to select from)"
+                ));
+            }
+        }
+    }
+
+    let (temp_dir, mut package_source_path): (Option<tempfile::TempDir>, PathBuf) = match &source_desc {
+        GitSource::Remote {
+            repo_url_for_clone,
+            git_ref,
+            path_in_repo,
+            ..
+        } => {
+            let temp_dir = clone_into_temp_dir(repo_url_for_clone, git_ref.as_deref())?;
+            let package_source_path = temp_dir.path().join(path_in_repo);
+            (Some(temp_dir), package_source_path)
+        }
+        GitSource::LocalGitRepo {
+            repo_path,
+            git_ref,
+            path_in_repo,
+        } => {
+            let repo_path_str = repo_path
+                .to_str()
+                .ok_or_else(|| anyhow!("Local repository path is not valid UTF-8: {}", repo_path.display()))?;
+            let temp_dir = clone_into_temp_dir(repo_path_str, git_ref.as_deref())?;
+            let package_source_path = temp_dir.path().join(path_in_repo);
+            (Some(temp_dir), package_source_path)
+        }
+        GitSource::LocalDirectory { path } => {
+            println!("Using local directory: {}", path.display());
+            (None, path.clone())
+        }
+    };
+
+    // Used to display paths relative to the search root in the
+    // multi-manifest prompt below, and in the not-found error; the initial
+    // package_source_path itself is already the search root.
+    let search_root = package_source_path.clone();
     let mut toml_in_cloned_path = package_source_path.join("typst.toml");
 
     if !toml_in_cloned_path.exists() {
@@ -401,8 +690,7 @@ fn handle_install_command(args: InstallArgs) -> Result<()> {
         if found_tomls.is_empty() {
             return Err(anyhow!(
                 "No typst.toml found directly or recursively in {}",
-                // If path_in_repo was specified, search was within that. Otherwise, repo root.
-                clone_target_dir.join(&source_desc.path_in_repo).display()
+                search_root.display()
             ));
         } else if found_tomls.len() == 1 {
             toml_in_cloned_path = found_tomls.remove(0);
@@ -420,7 +708,7 @@ fn handle_install_command(args: InstallArgs) -> Result<()> {
             println!("\nMultiple typst.toml files found. Please choose one to install:");
             for (i, path) in found_tomls.iter().enumerate() {
                 // Display path relative to the cloned repository root for clarity
-                let display_path = path.strip_prefix(&clone_target_dir).unwrap_or(path);
+                let display_path = path.strip_prefix(&search_root).unwrap_or(path);
                 println!("  {}: {}", i + 1, display_path.display());
             }
 
@@ -475,25 +763,60 @@ fn handle_install_command(args: InstallArgs) -> Result<()> {
             toml_in_cloned_path.display()
         )
     })?;
-    #[derive(Deserialize)]
-    struct PackageOnlyConfig {
-        package: config::PackageConfig,
-    }
-    let fetched_pkg_config_outer: PackageOnlyConfig = toml::from_str(&config_content)
-        .with_context(|| {
-            format!(
-                "Failed to parse typst.toml from {}",
-                toml_in_cloned_path.display()
-            )
-        })?;
-    let fetched_pkg_config = fetched_pkg_config_outer.package;
+    let pkg_config: PackageOnlyConfig = toml::from_str(&config_content).with_context(|| {
+        format!(
+            "Failed to parse typst.toml from {}",
+            toml_in_cloned_path.display()
+        )
+    })?;
 
     println!(
         "Found package: {} v{}",
-        fetched_pkg_config.name, fetched_pkg_config.version
+        pkg_config.package.name, pkg_config.package.version
     );
 
-    if let Some(required_compiler_str) = &fetched_pkg_config.compiler {
+    Ok(ResolvedSource {
+        _temp_dir: temp_dir,
+        package_source_path,
+        source_desc,
+        pkg_config,
+    })
+}
+
+/// Derive the `<provider-abbr>-<user-or-org>` namespace `install` stores a
+/// Git-sourced package under, e.g. `gh-typst` for a `github.com/typst/...` URL.
+fn typst_namespace_for(source_desc: &GitSource) -> String {
+    match source_desc {
+        GitSource::Remote {
+            provider_host,
+            user_or_org,
+            ..
+        } => {
+            let provider_abbr = match provider_host.as_str() {
+                "github.com" => "gh",
+                "gitlab.com" => "gl",
+                "bitbucket.org" => "bb",
+                _ => provider_host.split('.').next().unwrap_or("unk"),
+            };
+            format!("{}-{}", provider_abbr, user_or_org)
+        }
+        // Mirrors Typst's own `@local` namespace for packages developed
+        // on-disk rather than fetched from a registry or provider.
+        GitSource::LocalDirectory { .. } | GitSource::LocalGitRepo { .. } => "local".to_string(),
+    }
+}
+
+/// Validate a resolved source's compiler/entrypoint requirements and copy it
+/// into the Typst data directory, writing (and checking, under `--frozen`) a
+/// `typst.lock` alongside it.
+fn install_resolved_package(
+    resolved: &ResolvedSource,
+    flags: PackagingFlags,
+    frozen: bool,
+) -> Result<InstalledPackage> {
+    let pkg_config = &resolved.pkg_config.package;
+
+    if let Some(required_compiler_str) = &pkg_config.compiler {
         let required_version_req =
             semver::VersionReq::parse(required_compiler_str).with_context(|| {
                 format!(
@@ -515,29 +838,40 @@ fn handle_install_command(args: InstallArgs) -> Result<()> {
         );
     }
 
-    let data_dir = get_typst_data_dir()?;
-
-    let provider_abbr = match source_desc.provider_host.as_str() {
-        "github.com" => "gh",
-        "gitlab.com" => "gl",
-        "bitbucket.org" => "bb",
-        _ => source_desc.provider_host.split('.').next().unwrap_or("unk"),
-    };
+    let entrypoint = pkg_config.entrypoint.as_deref().unwrap_or("main.typ");
+    let entrypoint_path = resolved.package_source_path.join(entrypoint);
+    if !entrypoint_path.is_file() {
+        return Err(anyhow!(
+            "Package entrypoint '{}' not found at: {}",
+            entrypoint,
+            entrypoint_path.display()
+        ));
+    }
 
-    let typst_namespace_str = format!("{}-{}", provider_abbr, source_desc.user_or_org);
-    let typst_package_name_str = fetched_pkg_config.name.clone();
+    let data_dir = get_typst_data_dir()?;
+    let typst_namespace_str = typst_namespace_for(&resolved.source_desc);
+    let typst_package_name_str = pkg_config.name.clone();
 
     let final_install_dir = data_dir
         .join("packages")
         .join(&typst_namespace_str)
         .join(&typst_package_name_str)
-        .join(&fetched_pkg_config.version);
+        .join(&pkg_config.version);
+
+    let existing_lock = lockfile::read_lock(&final_install_dir)?;
+
+    if frozen && existing_lock.is_none() {
+        return Err(anyhow!(
+            "--frozen requires an existing typst.lock at {}, but none was found",
+            final_install_dir.display()
+        ));
+    }
 
     if final_install_dir.exists() {
         println!(
             "Package {} v{} already installed at {}. Overwriting.",
-            fetched_pkg_config.name,
-            fetched_pkg_config.version,
+            pkg_config.name,
+            pkg_config.version,
             final_install_dir.display()
         );
     }
@@ -551,125 +885,563 @@ fn handle_install_command(args: InstallArgs) -> Result<()> {
     println!("Installing to: {}", final_install_dir.display());
 
     let copy_files_import_base = format!("{}/{}", typst_namespace_str, typst_package_name_str);
+    let tool_config = resolved.pkg_config.tool.as_ref().and_then(|t| t.typm.as_ref());
+    let glob_options = resolve_glob_options(tool_config, &flags);
+    let respect_vcs_ignore = resolve_respect_vcs_ignore(tool_config, &flags);
 
     copy_files(
-        &package_source_path,
+        &resolved.package_source_path,
         &final_install_dir,
-        &fetched_pkg_config.exclude.clone().unwrap_or_default(),
+        &pkg_config.exclude.clone().unwrap_or_default(),
         &copy_files_import_base,
-        &fetched_pkg_config.version,
-        fetched_pkg_config
-            .entrypoint
-            .as_deref()
-            .unwrap_or("main.typ"),
+        &pkg_config.version,
+        glob_options,
+        respect_vcs_ignore,
     )?;
 
-    let import_statement = format!(
-        "#import \"@{}/{}:{}\": ...",
-        typst_namespace_str, typst_package_name_str, fetched_pkg_config.version
-    );
+    let new_lock = lockfile::compute_lock(&final_install_dir, &pkg_config.name, &pkg_config.version)?;
+
+    if let Some(old_lock) = &existing_lock {
+        if old_lock.integrity == new_lock.integrity {
+            println!("Integrity verified: matches existing typst.lock.");
+        } else {
+            let offending = lockfile::diff_files(old_lock, &new_lock);
+            if frozen {
+                return Err(anyhow!(
+                    "Integrity mismatch against existing typst.lock (--frozen): {} differ: {}",
+                    offending.len(),
+                    offending.join(", ")
+                ));
+            }
+            println!(
+                "WARNING: resolved source does not match the existing typst.lock! \
+                 {} file(s) differ: {}",
+                offending.len(),
+                offending.join(", ")
+            );
+        }
+    }
+
+    lockfile::write_lock(&final_install_dir, &new_lock)?;
+
     println!(
-        "\nPackage '{}' v{} installed successfully.",
-        fetched_pkg_config.name, fetched_pkg_config.version
+        "Package '{}' v{} installed successfully.",
+        pkg_config.name, pkg_config.version
     );
-    println!("You can now import it using: {}", import_statement);
+
+    Ok(InstalledPackage {
+        namespace: typst_namespace_str,
+        name: typst_package_name_str,
+        version: pkg_config.version.clone(),
+        install_dir: final_install_dir,
+    })
+}
+
+/// Find the highest installed version of `namespace/name` in the data dir
+/// that satisfies `version_req`, so an already-resolved dependency isn't
+/// re-cloned and reinstalled.
+fn find_satisfying_installed_version(
+    data_dir: &Path,
+    namespace: &str,
+    name: &str,
+    version_req: &semver::VersionReq,
+) -> Result<Option<semver::Version>> {
+    let package_dir = data_dir.join("packages").join(namespace).join(name);
+    if !package_dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut best: Option<semver::Version> = None;
+    for entry in fs::read_dir(&package_dir)
+        .with_context(|| format!("Failed to read package directory: {}", package_dir.display()))?
+    {
+        let entry = entry?;
+        if !entry.path().is_dir() {
+            continue;
+        }
+        let Ok(version) = semver::Version::parse(&entry.file_name().to_string_lossy()) else {
+            continue;
+        };
+        if version_req.matches(&version) && best.as_ref().map_or(true, |b| version > *b) {
+            best = Some(version);
+        }
+    }
+    Ok(best)
+}
+
+/// Recursively resolve and install `pkg_config`'s `[dependencies]` table,
+/// depth-first, so each dependency's own dependencies land before it does.
+/// `chain` is the `namespace/name` path currently being resolved, used to
+/// detect cycles; `installed` accumulates every package actually installed
+/// this run, in install (topological) order.
+fn resolve_dependencies(
+    pkg_config: &config::PackageConfig,
+    data_dir: &Path,
+    chain: &mut Vec<String>,
+    installed: &mut Vec<InstalledPackage>,
+    flags: PackagingFlags,
+) -> Result<()> {
+    let Some(dependencies) = &pkg_config.dependencies else {
+        return Ok(());
+    };
+
+    for (key, git_source) in dependencies {
+        let spec = deps::parse_dependency_spec(key, git_source)?;
+
+        // `spec.namespace` is just the `[dependencies]` key's namespace
+        // (e.g. `preview`), which has nothing to do with where `install`
+        // actually puts the package on disk. Resolve against the
+        // provider-derived namespace it will really be installed under, so
+        // dedup and cycle detection agree with the rest of `install`.
+        let install_namespace = typst_namespace_for(&parse_git_source(&spec.git_source)?);
+        let node_id = format!("{}/{}", install_namespace, spec.name);
+
+        if chain.contains(&node_id) {
+            return Err(anyhow!(
+                "Dependency cycle detected: {} -> {}",
+                chain.join(" -> "),
+                node_id
+            ));
+        }
+
+        if let Some(version) =
+            find_satisfying_installed_version(data_dir, &install_namespace, &spec.name, &spec.version_req)?
+        {
+            println!(
+                "  Dependency @{} already satisfied by installed v{}",
+                node_id, version
+            );
+            continue;
+        }
+
+        if let Some(already) = installed
+            .iter()
+            .find(|p| p.namespace == install_namespace && p.name == spec.name)
+        {
+            let already_version = semver::Version::parse(&already.version).with_context(|| {
+                format!(
+                    "Invalid version for already-installed dependency '@{}': {}",
+                    node_id, already.version
+                )
+            })?;
+            if !spec.version_req.matches(&already_version) {
+                return Err(anyhow!(
+                    "Dependency '@{}' requires {} but this run already installed v{} to satisfy another dependency",
+                    node_id,
+                    spec.version_req,
+                    already_version
+                ));
+            }
+            continue;
+        }
+
+        println!("Resolving dependency: @{} ({})", node_id, spec.version_req);
+        chain.push(node_id.clone());
+
+        let resolved = clone_and_locate_package(&spec.git_source, None)?;
+        resolve_dependencies(&resolved.pkg_config.package, data_dir, chain, installed, flags)?;
+
+        let resolved_version = semver::Version::parse(&resolved.pkg_config.package.version)
+            .with_context(|| {
+                format!(
+                    "Invalid version in dependency '@{}': {}",
+                    node_id, resolved.pkg_config.package.version
+                )
+            })?;
+        if !spec.version_req.matches(&resolved_version) {
+            return Err(anyhow!(
+                "Dependency '@{}' resolved to v{} which does not satisfy {}",
+                node_id,
+                resolved_version,
+                spec.version_req
+            ));
+        }
+
+        let installed_pkg = install_resolved_package(&resolved, flags, false)?;
+        installed.push(installed_pkg);
+        chain.pop();
+    }
 
     Ok(())
 }
 
-fn handle_list_command(args: ListArgs) -> Result<()> {
-    println!("Installed Typst packages:");
+fn handle_install_command(args: InstallArgs) -> Result<()> {
+    if let Some(spec) = registry::parse_registry_spec(&args.git_source) {
+        if args.git_ref_override().is_some() {
+            return Err(anyhow!(
+                "--rev/--tag/--branch only apply to a Git source, not a registry spec"
+            ));
+        }
+        let data_dir = get_typst_data_dir()?;
+        let cache_dir = get_typst_cache_dir()?;
+        let project_dir = std::env::current_dir().context("Failed to determine current directory")?;
+        registry::install_from_registry(&spec, &data_dir, &cache_dir, &project_dir)?;
+        return Ok(());
+    }
 
-    let mut found_packages_count = 0;
+    println!("Attempting to install from: {}", args.git_source);
 
+    let flags = args.packaging_flags();
+    let resolved = clone_and_locate_package(&args.git_source, args.git_ref_override())?;
     let data_dir = get_typst_data_dir()?;
-    let data_packages_root_dir = data_dir.join("packages");
 
+    let mut installed = Vec::new();
+    let mut chain = vec![format!(
+        "{}/{}",
+        typst_namespace_for(&resolved.source_desc),
+        resolved.pkg_config.package.name
+    )];
+    resolve_dependencies(
+        &resolved.pkg_config.package,
+        &data_dir,
+        &mut chain,
+        &mut installed,
+        flags,
+    )?;
+
+    let root = install_resolved_package(&resolved, flags, args.frozen)?;
+    let import_statement = format!(
+        "#import \"@{}/{}:{}\": ...",
+        root.namespace, root.name, root.version
+    );
+    installed.push(root);
+
+    println!("\nInstalled {} package(s):", installed.len());
+    for pkg in &installed {
+        println!(
+            "  @{}/{}:{} -> {}",
+            pkg.namespace,
+            pkg.name,
+            pkg.version,
+            pkg.install_dir.display()
+        );
+    }
+    println!("\nYou can now import the requested package using: {}", import_statement);
+
+    Ok(())
+}
+
+/// Re-hash the installed contents of every package pinned in the working
+/// directory's typm.lock (or just `args.spec`, if given) and compare against
+/// the digest recorded at install time.
+fn handle_verify_command(args: VerifyArgs) -> Result<()> {
+    let project_dir = std::env::current_dir().context("Failed to determine current directory")?;
+    let lock = project_lock::ProjectLock::load(&project_dir)?;
+    let data_dir = get_typst_data_dir()?;
     let cache_dir = get_typst_cache_dir()?;
-    let cache_packages_root_dir = cache_dir.join("packages");
 
-    let list_packages_in_root = |packages_root_dir: &Path, root_type: &str| -> Result<usize> {
-        let mut count = 0;
-        if !packages_root_dir.is_dir() {
+    let entries: Vec<&project_lock::LockedPackage> = match &args.spec {
+        Some(spec_str) => {
+            let spec = registry::parse_registry_spec(spec_str)
+                .ok_or_else(|| anyhow!("'{}' is not a valid @namespace/package:version spec", spec_str))?;
+            let entry = lock
+                .find(&spec.namespace, &spec.package, &spec.version)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No typm.lock entry for @{}/{}:{}",
+                        spec.namespace,
+                        spec.package,
+                        spec.version
+                    )
+                })?;
+            vec![entry]
+        }
+        None => lock.packages.iter().collect(),
+    };
+
+    if entries.is_empty() {
+        println!("No locked packages to verify.");
+        return Ok(());
+    }
+
+    let mut failures = Vec::new();
+    for entry in &entries {
+        let root = if entry.namespace == "local" { &data_dir } else { &cache_dir };
+        let install_dir = root
+            .join("packages")
+            .join(&entry.namespace)
+            .join(&entry.package)
+            .join(&entry.version);
+
+        if !install_dir.is_dir() {
             println!(
-                "  No packages found in {} directory ({} does not exist).",
-                root_type,
-                packages_root_dir.display()
+                "MISSING @{}/{}:{} (locked, but not installed at {})",
+                entry.namespace,
+                entry.package,
+                entry.version,
+                install_dir.display()
             );
-            return Ok(0);
-        }
-
-        for namespace_entry in fs::read_dir(packages_root_dir).with_context(|| {
-            format!(
-                "Failed to read {} packages directory: {}",
-                root_type,
-                packages_root_dir.display()
-            )
-        })? {
-            let namespace_entry = namespace_entry?;
-            let namespace_path = namespace_entry.path();
-            if !namespace_path.is_dir() {
+            failures.push(format!("@{}/{}:{}", entry.namespace, entry.package, entry.version));
+            continue;
+        }
+
+        let actual = project_lock::sha256_hex_of_dir(&install_dir)?;
+        if actual == entry.sha256 {
+            println!("OK      @{}/{}:{}", entry.namespace, entry.package, entry.version);
+        } else {
+            println!(
+                "MISMATCH @{}/{}:{} (expected {}, got {})",
+                entry.namespace, entry.package, entry.version, entry.sha256, actual
+            );
+            failures.push(format!("@{}/{}:{}", entry.namespace, entry.package, entry.version));
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} package(s) failed verification: {}",
+            failures.len(),
+            failures.join(", ")
+        ));
+    }
+
+    println!("All {} locked package(s) verified.", entries.len());
+    Ok(())
+}
+
+/// One namespace/package/version directory discovered under a packages root
+/// (any namespace in the data dir, such as `@local` or a Git-installed
+/// one, or the cache dir's registry packages). Shared between `list` and `search` so they walk the on-disk
+/// layout exactly once.
+struct DiscoveredPackage {
+    namespace: String,
+    package: String,
+    version: String,
+    root_type: &'static str,
+    path: PathBuf,
+}
+
+/// Read and parse an installed package's `typst.toml`. Shared by `list` and
+/// `search`; `install` parses the same `PackageOnlyConfig` shape itself.
+/// A missing or unparsable manifest means the install is incomplete or
+/// corrupt, which callers should treat as a warning rather than a hard
+/// failure of the whole listing/search.
+fn read_package_manifest(version_dir: &Path) -> Result<PackageOnlyConfig, PackageError> {
+    let manifest_path = version_dir.join("typst.toml");
+    let content = fs::read_to_string(&manifest_path).map_err(|source| {
+        if source.kind() == std::io::ErrorKind::NotFound {
+            PackageError::ManifestNotFound(manifest_path.clone())
+        } else {
+            PackageError::Io { path: manifest_path.clone(), source }
+        }
+    })?;
+    toml::from_str(&content).map_err(|source| PackageError::ManifestParse { path: manifest_path, source })
+}
+
+/// Warn when a discovered package's manifest disagrees with the
+/// namespace/package/version directory it was found in, since a stale or
+/// hand-edited manifest would otherwise silently import the wrong code.
+fn warn_on_manifest_mismatch(pkg: &DiscoveredPackage, manifest: &PackageOnlyConfig) {
+    if manifest.package.name != pkg.package {
+        eprintln!(
+            "Warning: @{}/{}:{} manifest declares name '{}', which doesn't match its directory name",
+            pkg.namespace, pkg.package, pkg.version, manifest.package.name
+        );
+    }
+    if manifest.package.version != pkg.version {
+        eprintln!(
+            "Warning: @{}/{}:{} manifest declares version '{}', which doesn't match its directory version",
+            pkg.namespace, pkg.package, pkg.version, manifest.package.version
+        );
+    }
+}
+
+/// Walk `packages_root_dir`'s `namespace/package/version` directory layout,
+/// returning every version directory found.
+fn walk_packages_in_root(
+    packages_root_dir: &Path,
+    root_type: &'static str,
+) -> Result<Vec<DiscoveredPackage>, PackageError> {
+    let mut found = Vec::new();
+    if !packages_root_dir.is_dir() {
+        return Err(PackageError::RootNotFound(packages_root_dir.to_path_buf()));
+    }
+
+    let read_dir = |dir: &Path| -> Result<fs::ReadDir, PackageError> {
+        fs::read_dir(dir).map_err(|source| PackageError::Io { path: dir.to_path_buf(), source })
+    };
+
+    for namespace_entry in read_dir(packages_root_dir)? {
+        let namespace_entry = namespace_entry.map_err(|source| PackageError::Io {
+            path: packages_root_dir.to_path_buf(),
+            source,
+        })?;
+        let namespace_path = namespace_entry.path();
+        if !namespace_path.is_dir() {
+            continue;
+        }
+        let namespace = namespace_entry.file_name().to_string_lossy().to_string();
+
+        for package_entry in read_dir(&namespace_path)? {
+            let package_entry = package_entry
+                .map_err(|source| PackageError::Io { path: namespace_path.clone(), source })?;
+            let package_path = package_entry.path();
+            if !package_path.is_dir() {
                 continue;
             }
-            let namespace_name = namespace_entry.file_name().to_string_lossy().to_string();
+            let package = package_entry.file_name().to_string_lossy().to_string();
 
-            for package_entry in fs::read_dir(&namespace_path).with_context(|| {
-                format!(
-                    "Failed to read namespace directory: {}",
-                    namespace_path.display()
-                )
-            })? {
-                let package_entry = package_entry?;
-                let package_path = package_entry.path();
-                if !package_path.is_dir() {
+            for version_entry in read_dir(&package_path)? {
+                let version_entry = version_entry
+                    .map_err(|source| PackageError::Io { path: package_path.clone(), source })?;
+                let version_path = version_entry.path();
+                if !version_path.is_dir() {
                     continue;
                 }
-                let package_name = package_entry.file_name().to_string_lossy().to_string();
+                let version = version_entry.file_name().to_string_lossy().to_string();
+
+                found.push(DiscoveredPackage {
+                    namespace: namespace.clone(),
+                    package: package.clone(),
+                    version,
+                    root_type,
+                    path: version_path,
+                });
+            }
+        }
+    }
 
-                for version_entry in fs::read_dir(&package_path).with_context(|| {
-                    format!(
-                        "Failed to read package directory: {}",
-                        package_path.display()
-                    )
-                })? {
-                    let version_entry = version_entry?;
-                    let version_path = version_entry.path();
-                    if !version_path.is_dir() {
-                        continue;
-                    }
-                    let version_name = version_entry.file_name().to_string_lossy().to_string();
+    Ok(found)
+}
 
-                    println!("  @{}/{}:{}", namespace_name, package_name, version_name);
-                    count += 1;
-                }
-            }
+/// A `list`/`search` result in a shape `serde_json` can emit directly,
+/// carrying the same metadata `print_record_metadata` prints for text
+/// output.
+#[derive(serde::Serialize)]
+struct PackageRecord {
+    namespace: String,
+    package: String,
+    version: String,
+    source: &'static str,
+    path: PathBuf,
+    entrypoint: Option<String>,
+    compiler: Option<String>,
+    authors: Option<Vec<String>>,
+    description: Option<String>,
+    manifest_ok: bool,
+}
+
+/// Read `pkg`'s manifest (warning on disk about mismatches/corruption as a
+/// side effect, same as the text path) and fold it into a `PackageRecord`.
+fn build_record(pkg: &DiscoveredPackage) -> PackageRecord {
+    let manifest = read_package_manifest(&pkg.path);
+    let manifest_ok = match &manifest {
+        Ok(manifest) => {
+            warn_on_manifest_mismatch(pkg, manifest);
+            true
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: @{}/{}:{} looks incomplete or corrupt: {}",
+                pkg.namespace, pkg.package, pkg.version, e
+            );
+            false
         }
-        Ok(count)
     };
+    let package = manifest.ok().map(|m| m.package);
+
+    PackageRecord {
+        namespace: pkg.namespace.clone(),
+        package: pkg.package.clone(),
+        version: pkg.version.clone(),
+        source: pkg.root_type,
+        path: pkg.path.clone(),
+        entrypoint: package.as_ref().and_then(|p| p.entrypoint.clone()),
+        compiler: package.as_ref().and_then(|p| p.compiler.clone()),
+        authors: package.as_ref().and_then(|p| p.authors.clone()),
+        description: package.as_ref().and_then(|p| p.description.clone()),
+        manifest_ok,
+    }
+}
+
+/// Print the metadata fields under a package's already-printed
+/// `@namespace/name:version` heading line.
+fn print_record_metadata(record: &PackageRecord) {
+    if let Some(entrypoint) = &record.entrypoint {
+        println!("      entrypoint: {}", entrypoint);
+    }
+    if let Some(compiler) = &record.compiler {
+        println!("      compiler: {}", compiler);
+    }
+    if let Some(authors) = &record.authors {
+        if !authors.is_empty() {
+            println!("      authors: {}", authors.join(", "));
+        }
+    }
+    if let Some(description) = &record.description {
+        println!("      description: {}", description);
+    }
+}
+
+fn handle_list_command(args: ListArgs) -> Result<()> {
+    let data_dir = get_typst_data_dir()?;
+    let data_packages_root_dir = data_dir.join("packages");
+
+    let cache_dir = get_typst_cache_dir()?;
+    let cache_packages_root_dir = cache_dir.join("packages");
 
     let list_local = args.local;
     let list_preview = args.preview;
     let list_all = !list_local && !list_preview;
 
+    let mut local_records = Vec::new();
+    let mut preview_records = Vec::new();
+
+    if list_local || list_all {
+        match walk_packages_in_root(&data_packages_root_dir, "data") {
+            Ok(found) => local_records = found.iter().map(build_record).collect(),
+            Err(PackageError::RootNotFound(_)) => {}
+            Err(e) => eprintln!("Warning: Could not list packages from data directory: {}", e),
+        }
+    }
+
+    if list_preview || list_all {
+        match walk_packages_in_root(&cache_packages_root_dir, "cache") {
+            Ok(found) => preview_records = found.iter().map(build_record).collect(),
+            Err(PackageError::RootNotFound(_)) => {}
+            Err(e) => eprintln!("Warning: Could not list packages from cache directory: {}", e),
+        }
+    }
+
+    let found_packages_count = local_records.len() + preview_records.len();
+
+    if matches!(args.format, config::OutputFormat::Json) {
+        let all_records: Vec<&PackageRecord> = local_records.iter().chain(preview_records.iter()).collect();
+        let output = serde_json::json!({
+            "found_packages_count": found_packages_count,
+            "packages": all_records,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("Installed Typst packages:");
+
     if list_local || list_all {
         println!("\nLocal packages (data directory):");
-        match list_packages_in_root(&data_packages_root_dir, "data") {
-            Ok(count) => found_packages_count += count,
-            Err(e) => eprintln!(
-                "Warning: Could not list packages from data directory: {}",
-                e
-            ),
+        if local_records.is_empty() && !data_packages_root_dir.is_dir() {
+            println!(
+                "  No packages found in data directory ({} does not exist).",
+                data_packages_root_dir.display()
+            );
+        }
+        for record in &local_records {
+            println!("  @{}/{}:{}", record.namespace, record.package, record.version);
+            print_record_metadata(record);
         }
     }
 
     if list_preview || list_all {
         println!("\nPreview packages (cache directory):");
-        match list_packages_in_root(&cache_packages_root_dir, "cache") {
-            Ok(count) => found_packages_count += count,
-            Err(e) => eprintln!(
-                "Warning: Could not list packages from cache directory: {}",
-                e
-            ),
+        if preview_records.is_empty() && !cache_packages_root_dir.is_dir() {
+            println!(
+                "  No packages found in cache directory ({} does not exist).",
+                cache_packages_root_dir.display()
+            );
+        }
+        for record in &preview_records {
+            println!("  @{}/{}:{}", record.namespace, record.package, record.version);
+            print_record_metadata(record);
         }
     }
 
@@ -685,3 +1457,287 @@ fn handle_list_command(args: ListArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Translate a `*`/`?` glob query into a case-insensitive regex; queries
+/// without wildcards fall back to a plain substring match.
+fn name_matches(query: &str, name: &str) -> bool {
+    if !query.contains('*') && !query.contains('?') {
+        return name.to_lowercase().contains(&query.to_lowercase());
+    }
+
+    let mut body = String::new();
+    for c in query.chars() {
+        match c {
+            '*' => body.push_str(".*"),
+            '?' => body.push('.'),
+            c => body.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    let pattern = format!("(?i)^{}$", body);
+    regex::Regex::new(&pattern)
+        .map(|re| re.is_match(name))
+        .unwrap_or(false)
+}
+
+fn handle_search_command(args: SearchArgs) -> Result<()> {
+    let version_req = args
+        .version
+        .as_deref()
+        .map(semver::VersionReq::parse)
+        .transpose()
+        .context("Invalid --version requirement")?;
+
+    let data_dir = get_typst_data_dir()?;
+    let cache_dir = get_typst_cache_dir()?;
+
+    let walk_or_empty = |root: &Path, root_type: &'static str| -> Result<Vec<DiscoveredPackage>> {
+        match walk_packages_in_root(root, root_type) {
+            Ok(found) => Ok(found),
+            Err(PackageError::RootNotFound(_)) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    };
+
+    let mut found = walk_or_empty(&data_dir.join("packages"), "data")?;
+    found.extend(walk_or_empty(&cache_dir.join("packages"), "cache")?);
+
+    found.retain(|pkg| {
+        if !name_matches(&args.query, &pkg.package) {
+            return false;
+        }
+        if let Some(namespace) = &args.namespace {
+            if &pkg.namespace != namespace {
+                return false;
+            }
+        }
+        if let Some(req) = &version_req {
+            match semver::Version::parse(&pkg.version) {
+                Ok(v) => {
+                    if !req.matches(&v) {
+                        return false;
+                    }
+                }
+                Err(_) => return false,
+            }
+        }
+        true
+    });
+
+    if args.latest {
+        let mut best: std::collections::HashMap<(String, String), DiscoveredPackage> = std::collections::HashMap::new();
+        for pkg in found {
+            let key = (pkg.namespace.clone(), pkg.package.clone());
+            let is_better = match best.get(&key) {
+                Some(current) => match (semver::Version::parse(&pkg.version), semver::Version::parse(&current.version)) {
+                    (Ok(new_v), Ok(cur_v)) => new_v > cur_v,
+                    _ => false,
+                },
+                None => true,
+            };
+            if is_better {
+                best.insert(key, pkg);
+            }
+        }
+        found = best.into_values().collect();
+        found.sort_by(|a, b| (&a.namespace, &a.package).cmp(&(&b.namespace, &b.package)));
+    } else {
+        found.sort_by(|a, b| (&a.namespace, &a.package, &a.version).cmp(&(&b.namespace, &b.package, &b.version)));
+    }
+
+    let records: Vec<PackageRecord> = found.iter().map(build_record).collect();
+
+    if matches!(args.format, config::OutputFormat::Json) {
+        let output = serde_json::json!({
+            "found_packages_count": records.len(),
+            "packages": records,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if records.is_empty() {
+        println!("No installed packages matched '{}'.", args.query);
+        return Ok(());
+    }
+
+    println!("Found {} matching package(s):", records.len());
+    for (pkg, record) in found.iter().zip(&records) {
+        println!(
+            "  @{}/{}:{} ({}) -> {}",
+            pkg.namespace,
+            pkg.package,
+            pkg.version,
+            pkg.root_type,
+            pkg.path.display()
+        );
+        print_record_metadata(record);
+    }
+
+    Ok(())
+}
+
+/// Parse a `30d`/`12h`/`45m`/`90s`-style duration into a `Duration`.
+fn parse_duration(spec: &str) -> Result<std::time::Duration> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Err(anyhow!("Invalid duration '': expected a number followed by s/m/h/d"));
+    }
+    let (number, unit) = spec.split_at(spec.len() - 1);
+    let amount: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration '{}': expected a number followed by s/m/h/d", spec))?;
+    let seconds = match unit {
+        "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 60 * 60,
+        "d" => amount * 60 * 60 * 24,
+        _ => {
+            return Err(anyhow!(
+                "Invalid duration '{}': expected a unit suffix of s/m/h/d",
+                spec
+            ))
+        }
+    };
+    Ok(std::time::Duration::from_secs(seconds))
+}
+
+/// Sum the size of every file under `dir`, for reporting reclaimed space.
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry.with_context(|| format!("Failed to walk: {}", dir.display()))?;
+        if entry.file_type().is_file() {
+            total += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+    }
+    Ok(total)
+}
+
+fn handle_prune_command(args: PruneArgs) -> Result<()> {
+    let data_dir = get_typst_data_dir()?;
+    let cache_dir = get_typst_cache_dir()?;
+
+    let walk_or_empty = |root: &Path, root_type: &'static str| -> Result<Vec<DiscoveredPackage>> {
+        match walk_packages_in_root(root, root_type) {
+            Ok(found) => Ok(found),
+            Err(PackageError::RootNotFound(_)) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    };
+
+    let mut installed = walk_or_empty(&data_dir.join("packages"), "data")?;
+    installed.extend(walk_or_empty(&cache_dir.join("packages"), "cache")?);
+
+    if let Some(namespace) = &args.namespace {
+        installed.retain(|pkg| &pkg.namespace == namespace);
+    }
+
+    let project_dir = std::env::current_dir().context("Failed to determine current directory")?;
+    let lock = project_lock::ProjectLock::load(&project_dir)?;
+
+    let mut candidates: Vec<&DiscoveredPackage> = Vec::new();
+
+    if !args.specs.is_empty() {
+        for spec_str in &args.specs {
+            let spec = registry::parse_registry_spec(spec_str)
+                .ok_or_else(|| anyhow!("'{}' is not a valid @namespace/package:version spec", spec_str))?;
+            match installed.iter().find(|pkg| {
+                pkg.namespace == spec.namespace && pkg.package == spec.package && pkg.version == spec.version
+            }) {
+                Some(pkg) => candidates.push(pkg),
+                None => eprintln!("Warning: {} is not installed, skipping", spec_str),
+            }
+        }
+    } else {
+        if args.keep_latest.is_none() && args.older_than.is_none() {
+            return Err(anyhow!(
+                "Specify --keep-latest, --older-than, or one or more explicit @namespace/package:version specs"
+            ));
+        }
+
+        let mut by_keep_latest: std::collections::HashSet<(String, String, String)> = std::collections::HashSet::new();
+        if let Some(keep_latest) = args.keep_latest {
+            let mut by_package: std::collections::HashMap<(String, String), Vec<&DiscoveredPackage>> =
+                std::collections::HashMap::new();
+            for pkg in &installed {
+                by_package
+                    .entry((pkg.namespace.clone(), pkg.package.clone()))
+                    .or_default()
+                    .push(pkg);
+            }
+            for versions in by_package.values_mut() {
+                versions.sort_by(|a, b| {
+                    match (semver::Version::parse(&a.version), semver::Version::parse(&b.version)) {
+                        (Ok(va), Ok(vb)) => vb.cmp(&va),
+                        _ => b.version.cmp(&a.version),
+                    }
+                });
+                for pkg in versions.iter().skip(keep_latest) {
+                    by_keep_latest.insert((pkg.namespace.clone(), pkg.package.clone(), pkg.version.clone()));
+                }
+            }
+        }
+
+        let older_than_cutoff = args
+            .older_than
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?
+            .map(|max_age| std::time::SystemTime::now() - max_age);
+
+        for pkg in &installed {
+            let key = (pkg.namespace.clone(), pkg.package.clone(), pkg.version.clone());
+            let matches_keep_latest = by_keep_latest.contains(&key);
+            let matches_older_than = older_than_cutoff.is_some_and(|cutoff| {
+                fs::metadata(&pkg.path)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| modified < cutoff)
+            });
+
+            if matches_keep_latest || matches_older_than {
+                candidates.push(pkg);
+            }
+        }
+    }
+
+    let mut reclaimed: u64 = 0;
+    let mut removed = 0;
+
+    for pkg in candidates {
+        if lock.find(&pkg.namespace, &pkg.package, &pkg.version).is_some() {
+            println!(
+                "Skipping @{}/{}:{} (pinned by typm.lock)",
+                pkg.namespace, pkg.package, pkg.version
+            );
+            continue;
+        }
+
+        let size = dir_size(&pkg.path).unwrap_or(0);
+
+        if args.dry_run {
+            println!(
+                "Would remove @{}/{}:{} ({} bytes) at {}",
+                pkg.namespace, pkg.package, pkg.version, size, pkg.path.display()
+            );
+        } else {
+            fs::remove_dir_all(&pkg.path)
+                .with_context(|| format!("Failed to remove: {}", pkg.path.display()))?;
+            println!(
+                "Removed @{}/{}:{} ({} bytes)",
+                pkg.namespace, pkg.package, pkg.version, size
+            );
+        }
+        reclaimed += size;
+        removed += 1;
+    }
+
+    if removed == 0 {
+        println!("Nothing to prune.");
+    } else if args.dry_run {
+        println!("\nWould reclaim {} bytes across {} package version(s).", reclaimed, removed);
+    } else {
+        println!("\nReclaimed {} bytes across {} package version(s).", reclaimed, removed);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! A structured error type for package discovery (`list`/`search`) and
+//! manifest parsing, so callers can match on failure kind instead of
+//! grepping ad-hoc warning strings.
+
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum PackageError {
+    /// A packages root directory (the data or cache dir's `packages`
+    /// subdirectory) doesn't exist.
+    RootNotFound(PathBuf),
+    /// A version directory has no `typst.toml` at all.
+    ManifestNotFound(PathBuf),
+    /// A `typst.toml` exists but isn't valid TOML, or doesn't match the
+    /// expected manifest shape.
+    ManifestParse { path: PathBuf, source: toml::de::Error },
+    /// Any other I/O failure while discovering or reading a package.
+    Io { path: PathBuf, source: std::io::Error },
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageError::RootNotFound(path) => {
+                write!(f, "Package root does not exist: {}", path.display())
+            }
+            PackageError::ManifestNotFound(path) => {
+                write!(f, "Missing manifest: {}", path.display())
+            }
+            PackageError::ManifestParse { path, source } => {
+                write!(f, "Failed to parse manifest {}: {}", path.display(), source)
+            }
+            PackageError::Io { path, source } => {
+                write!(f, "I/O error at {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PackageError::ManifestParse { source, .. } => Some(source),
+            PackageError::Io { source, .. } => Some(source),
+            PackageError::RootNotFound(_) | PackageError::ManifestNotFound(_) => None,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! `typm.lock`: a project-level manifest pinning the registry packages a
+//! working directory has installed, for reproducible installs and tamper
+//! detection (mirrors how rustc's bootstrap/dist tooling pins component
+//! hashes).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const LOCK_FILE_NAME: &str = "typm.lock";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub namespace: String,
+    pub package: String,
+    pub version: String,
+    pub source_url: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProjectLock {
+    #[serde(default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl ProjectLock {
+    /// Load `typm.lock` from `project_dir`, or an empty lock if it doesn't
+    /// exist yet.
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let path = project_dir.join(LOCK_FILE_NAME);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read lockfile: {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse lockfile: {}", path.display()))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let path = project_dir.join(LOCK_FILE_NAME);
+        let content = toml::to_string_pretty(self).context("Failed to serialize typm.lock")?;
+        fs::write(&path, content).with_context(|| format!("Failed to write lockfile: {}", path.display()))
+    }
+
+    pub fn find(&self, namespace: &str, package: &str, version: &str) -> Option<&LockedPackage> {
+        self.packages
+            .iter()
+            .find(|p| p.namespace == namespace && p.package == package && p.version == version)
+    }
+
+    /// Replace any existing entry for the same `(namespace, package,
+    /// version)` with `entry`, keeping the list sorted for a stable diff.
+    pub fn upsert(&mut self, entry: LockedPackage) {
+        self.packages
+            .retain(|p| !(p.namespace == entry.namespace && p.package == entry.package && p.version == entry.version));
+        self.packages.push(entry);
+        self.packages
+            .sort_by(|a, b| (&a.namespace, &a.package, &a.version).cmp(&(&b.namespace, &b.package, &b.version)));
+    }
+}
+
+/// Hash a directory's file contents (path and bytes, in sorted relative-path
+/// order) into a single SHA-256 digest, so the result is deterministic
+/// regardless of extraction or filesystem walk order.
+pub fn sha256_hex_of_dir(dir: &Path) -> Result<String> {
+    let mut paths: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    paths.sort();
+
+    let mut hasher = Sha256::new();
+    for path in paths {
+        let rel = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        hasher.update(rel.as_bytes());
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read file for hashing: {}", path.display()))?;
+        hasher.update(&bytes);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,249 @@
+//! Installing a package by `@namespace/package:version` spec straight from a
+//! package registry (Typst Universe, for the `preview` namespace), as
+//! opposed to `install`'s Git-source clone path.
+
+use crate::project_lock::{self, LockedPackage, ProjectLock};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// A parsed `@namespace/package:version` registry spec.
+pub struct RegistrySpec {
+    pub namespace: String,
+    pub package: String,
+    pub version: String,
+}
+
+/// Parse a `@namespace/package:version` registry spec. Returns `None` for
+/// anything that isn't one (Git URLs, provider aliases, local paths), so
+/// callers can fall through to the Git-source install path.
+pub fn parse_registry_spec(spec: &str) -> Option<RegistrySpec> {
+    let rest = spec.strip_prefix('@')?;
+    let (namespace, rest) = rest.split_once('/')?;
+    let (package, version) = rest.split_once(':')?;
+    if namespace.is_empty() || package.is_empty() || version.is_empty() {
+        return None;
+    }
+    Some(RegistrySpec {
+        namespace: namespace.to_string(),
+        package: package.to_string(),
+        version: version.to_string(),
+    })
+}
+
+/// Download and unpack an `@preview` `spec` into the cache dir's package
+/// root (matching how Typst itself caches registry packages). An `@local`
+/// spec is never downloaded: it must already be installed under the data
+/// dir, and is only hashed and pinned. Any other namespace is an error.
+/// Retries the download with backoff and only renames the extracted
+/// contents into place after a complete, successful unpack, so an
+/// interrupted install never leaves a half-populated version directory.
+///
+/// The extracted contents are hashed and pinned in `project_dir`'s
+/// `typm.lock`. If that lock already has an entry for this exact
+/// `namespace/package/version`, the freshly computed digest must match it,
+/// or the install is refused outright.
+pub fn install_from_registry(
+    spec: &RegistrySpec,
+    data_dir: &Path,
+    cache_dir: &Path,
+    project_dir: &Path,
+) -> Result<PathBuf> {
+    if spec.namespace != "preview" && spec.namespace != "local" {
+        return Err(anyhow!(
+            "Don't know how to fetch namespace '@{}' from a registry (only '@preview' is served by Typst Universe; '@local' packages are expected to already be on disk)",
+            spec.namespace
+        ));
+    }
+
+    let root = if spec.namespace == "local" { data_dir } else { cache_dir };
+    let final_dir = root
+        .join("packages")
+        .join(&spec.namespace)
+        .join(&spec.package)
+        .join(&spec.version);
+
+    let mut lock = ProjectLock::load(project_dir)?;
+
+    if spec.namespace == "local" && !final_dir.is_dir() {
+        return Err(anyhow!(
+            "@local/{}:{} is not installed, and '@local' packages aren't served by a registry; place it under {} yourself",
+            spec.package,
+            spec.version,
+            final_dir.display()
+        ));
+    }
+
+    let url = format!(
+        "https://packages.typst.org/{}/{}-{}.tar.gz",
+        spec.namespace, spec.package, spec.version
+    );
+
+    if final_dir.is_dir() {
+        let digest = project_lock::sha256_hex_of_dir(&final_dir)?;
+        verify_against_lock(&lock, spec, &digest)?;
+        lock.upsert(locked_package(spec, &url, digest));
+        lock.save(project_dir)?;
+        println!(
+            "@{}/{}:{} is already installed at {}",
+            spec.namespace,
+            spec.package,
+            spec.version,
+            final_dir.display()
+        );
+        return Ok(final_dir);
+    }
+
+    let archive = download_with_retries(&url)?;
+
+    let mut staging_name = final_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("Install path has no file name: {}", final_dir.display()))?
+        .to_os_string();
+    staging_name.push(".tmp-extract");
+    let staging_dir = final_dir.with_file_name(staging_name);
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)
+            .with_context(|| format!("Failed to clear stale staging directory: {}", staging_dir.display()))?;
+    }
+    extract_tarball(archive.path(), &staging_dir)?;
+
+    let digest = project_lock::sha256_hex_of_dir(&staging_dir)?;
+    if let Err(e) = verify_against_lock(&lock, spec, &digest) {
+        fs::remove_dir_all(&staging_dir).ok();
+        return Err(e);
+    }
+
+    let parent = final_dir
+        .parent()
+        .ok_or_else(|| anyhow!("Install path has no parent directory: {}", final_dir.display()))?;
+    fs::create_dir_all(parent).with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    fs::rename(&staging_dir, &final_dir)
+        .with_context(|| format!("Failed to move extracted package into place: {}", final_dir.display()))?;
+
+    lock.upsert(locked_package(spec, &url, digest));
+    lock.save(project_dir)?;
+
+    println!(
+        "Installed @{}/{}:{} to {}",
+        spec.namespace,
+        spec.package,
+        spec.version,
+        final_dir.display()
+    );
+    Ok(final_dir)
+}
+
+fn locked_package(spec: &RegistrySpec, url: &str, sha256: String) -> LockedPackage {
+    LockedPackage {
+        namespace: spec.namespace.clone(),
+        package: spec.package.clone(),
+        version: spec.version.clone(),
+        source_url: url.to_string(),
+        sha256,
+    }
+}
+
+/// Refuse the install if `typm.lock` already pins this exact
+/// `namespace/package/version` to a different digest.
+fn verify_against_lock(lock: &ProjectLock, spec: &RegistrySpec, digest: &str) -> Result<()> {
+    if let Some(locked) = lock.find(&spec.namespace, &spec.package, &spec.version) {
+        if locked.sha256 != digest {
+            return Err(anyhow!(
+                "Integrity check failed for @{}/{}:{}: typm.lock expects {} but got {}",
+                spec.namespace,
+                spec.package,
+                spec.version,
+                locked.sha256,
+                digest
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Download `url` to a temp file, retrying with exponential backoff on
+/// failure, modeled on rustc's bootstrap downloader.
+fn download_with_retries(url: &str) -> Result<tempfile::NamedTempFile> {
+    let mut last_err = None;
+    for attempt in 1..=MAX_DOWNLOAD_ATTEMPTS {
+        println!("Downloading {} (attempt {}/{})...", url, attempt, MAX_DOWNLOAD_ATTEMPTS);
+        match try_download(url) {
+            Ok(file) => return Ok(file),
+            Err(e) => {
+                eprintln!("Download attempt {} failed: {}", attempt, e);
+                last_err = Some(e);
+                if attempt < MAX_DOWNLOAD_ATTEMPTS {
+                    thread::sleep(Duration::from_secs(2u64.pow(attempt - 1)));
+                }
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow!("Failed to download {}", url)))
+}
+
+fn try_download(url: &str) -> Result<tempfile::NamedTempFile> {
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Request failed: {}", url))?;
+
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let mut tmp_file = tempfile::Builder::new()
+        .prefix("typm-download-")
+        .suffix(".tar.gz")
+        .tempfile()
+        .context("Failed to create temp file for download")?;
+
+    let mut reader = response.into_reader();
+    let mut buf = [0u8; 64 * 1024];
+    let mut downloaded: u64 = 0;
+    loop {
+        let n = reader.read(&mut buf).context("Failed reading response body")?;
+        if n == 0 {
+            break;
+        }
+        tmp_file
+            .write_all(&buf[..n])
+            .context("Failed writing downloaded bytes to temp file")?;
+        downloaded += n as u64;
+        print_progress(downloaded, content_length);
+    }
+    println!();
+    tmp_file.flush().context("Failed to flush downloaded temp file")?;
+
+    Ok(tmp_file)
+}
+
+fn print_progress(downloaded: u64, total: Option<u64>) {
+    match total {
+        Some(total) if total > 0 => {
+            let percent = (downloaded as f64 / total as f64 * 100.0).min(100.0);
+            print!("\r  {:>3.0}% ({} / {} bytes)", percent, downloaded, total);
+        }
+        _ => print!("\r  {} bytes downloaded", downloaded),
+    }
+    let _ = std::io::stdout().flush();
+}
+
+fn extract_tarball(archive_path: &Path, dest_dir: &Path) -> Result<()> {
+    fs::create_dir_all(dest_dir)
+        .with_context(|| format!("Failed to create extraction directory: {}", dest_dir.display()))?;
+
+    let file = fs::File::open(archive_path)
+        .with_context(|| format!("Failed to open downloaded archive: {}", archive_path.display()))?;
+    let gz = flate2::read::GzDecoder::new(file);
+    let mut archive = tar::Archive::new(gz);
+    archive
+        .unpack(dest_dir)
+        .with_context(|| format!("Failed to unpack archive into: {}", dest_dir.display()))?;
+
+    Ok(())
+}